@@ -0,0 +1,21 @@
+//! Generate the JSON Schema of the websocket protocol. Writes one schema file
+//! per message direction into the given directory, or the current directory.
+
+use std::{fs, path::PathBuf};
+
+use api_types::websocket::{ClientMessage, ServerEvent};
+use schemars::{schema::RootSchema, schema_for};
+
+fn main() -> std::io::Result<()> {
+	let dir = std::env::args_os().nth(1).map_or_else(PathBuf::new, PathBuf::from);
+	write_schema(&dir.join("server-event.schema.json"), &schema_for!(ServerEvent))?;
+	write_schema(&dir.join("client-message.schema.json"), &schema_for!(ClientMessage))?;
+	Ok(())
+}
+
+/// Write the schema to the file as pretty-printed JSON.
+fn write_schema(path: &PathBuf, schema: &RootSchema) -> std::io::Result<()> {
+	let json = serde_json::to_string_pretty(schema)?;
+	fs::write(path, json + "\n")?;
+	Ok(())
+}
@@ -0,0 +1,203 @@
+//! Encoding of websocket messages
+
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+/// Error of encoding or decoding a websocket message
+#[derive(Debug, Error)]
+pub enum CodecError {
+	/// JSON error
+	#[error("{0}")]
+	Json(#[from] serde_json::Error),
+	/// MessagePack encoding error
+	#[error("{0}")]
+	MessagePackEncode(#[from] rmp_serde::encode::Error),
+	/// MessagePack decoding error
+	#[error("{0}")]
+	MessagePackDecode(#[from] rmp_serde::decode::Error),
+	/// CBOR encoding error
+	#[error("{0}")]
+	CborEncode(#[from] ciborium::ser::Error<std::io::Error>),
+	/// CBOR decoding error
+	#[error("{0}")]
+	CborDecode(#[from] ciborium::de::Error<std::io::Error>),
+}
+
+/// Websocket frame of an encoded message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+	/// Text frame
+	Text(String),
+	/// Binary frame
+	Binary(Vec<u8>),
+}
+
+/// Encoding of websocket messages, negotiated by websocket subprotocol. Without
+/// subprotocol, messages are encoded as JSON.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Codec {
+	/// JSON in text frames
+	#[default]
+	Json,
+	/// MessagePack in binary frames
+	MessagePack,
+	/// CBOR in binary frames
+	Cbor,
+}
+
+impl Codec {
+	/// All codecs
+	pub const ALL: [Self; 3] = [Self::Json, Self::MessagePack, Self::Cbor];
+
+	/// Websocket subprotocol selecting the codec.
+	#[must_use]
+	pub const fn subprotocol(self) -> &'static str {
+		match self {
+			Self::Json => "buzzer.json",
+			Self::MessagePack => "buzzer.msgpack",
+			Self::Cbor => "buzzer.cbor",
+		}
+	}
+
+	/// Select the codec of the first supported subprotocol in the
+	/// comma-separated list of the `Sec-WebSocket-Protocol` header. None if no
+	/// subprotocol is supported.
+	#[must_use]
+	pub fn negotiate(protocols: &str) -> Option<Self> {
+		protocols.split(',').find_map(|protocol| {
+			Self::ALL.into_iter().find(|codec| codec.subprotocol() == protocol.trim())
+		})
+	}
+
+	/// Encode a message.
+	pub fn encode<T: Serialize>(self, message: &T) -> Result<Frame, CodecError> {
+		Ok(match self {
+			Self::Json => Frame::Text(serde_json::to_string(message)?),
+			Self::MessagePack => Frame::Binary(rmp_serde::to_vec_named(message)?),
+			Self::Cbor => {
+				let mut data = Vec::new();
+				ciborium::ser::into_writer(message, &mut data)?;
+				Frame::Binary(data)
+			}
+		})
+	}
+
+	/// Decode a message. Text frames are always decoded as JSON, so clients of
+	/// binary codecs can still send JSON.
+	pub fn decode<T: DeserializeOwned>(self, frame: &Frame) -> Result<T, CodecError> {
+		Ok(match (frame, self) {
+			(Frame::Text(text), _) => serde_json::from_str(text)?,
+			(Frame::Binary(data), Self::Json) => serde_json::from_slice(data)?,
+			(Frame::Binary(data), Self::MessagePack) => rmp_serde::from_slice(data)?,
+			(Frame::Binary(data), Self::Cbor) => ciborium::de::from_reader(data.as_slice())?,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde::{de::DeserializeOwned, Serialize};
+	use uuid::Uuid;
+
+	use super::{Codec, CodecError, Frame};
+	use crate::websocket::{BuzzEntry, ClientMessage, Hello, ServerEvent, ServerMessage};
+
+	/// Server messages covering unit, newtype and struct variants and nested
+	/// optional fields
+	fn server_events() -> Vec<ServerEvent> {
+		let entry = BuzzEntry {
+			id: Uuid::new_v4(),
+			name: "Alice".to_owned(),
+			team: None,
+			delta_ms: 12.5,
+			rtt_ms: Some(40.0),
+		};
+		[
+			ServerMessage::Welcome(Hello::current()),
+			ServerMessage::Buzzed(Some(entry.clone())),
+			ServerMessage::Buzzed(None),
+			ServerMessage::BuzzQueue(vec![entry]),
+			ServerMessage::Kicked { id: Uuid::new_v4(), reason: Some("Spoilers".to_owned()) },
+			ServerMessage::Closed,
+			ServerMessage::Tick(1500.0),
+		]
+		.into_iter()
+		.enumerate()
+		.map(|(seq, message)| ServerEvent { seq: seq as u64, message })
+		.collect()
+	}
+
+	/// Client messages covering unit, newtype and struct variants
+	fn client_messages() -> Vec<ClientMessage> {
+		vec![
+			ClientMessage::Hello(Hello::current()),
+			ClientMessage::Buzz { pressed_at: Some(1_650_000_000_000.5) },
+			ClientMessage::Buzz { pressed_at: None },
+			ClientMessage::FreeBuzzer,
+		]
+	}
+
+	/// Encode and decode the message with the codec and check that it is
+	/// unchanged, comparing the JSON representation.
+	fn assert_round_trip<T: Serialize + DeserializeOwned>(
+		codec: Codec,
+		message: &T,
+	) -> Result<(), CodecError> {
+		let frame = codec.encode(message)?;
+		match (codec, &frame) {
+			(Codec::Json, Frame::Text(_))
+			| (Codec::MessagePack | Codec::Cbor, Frame::Binary(_)) => {}
+			_ => panic!("{codec:?} encoded to the wrong frame type: {frame:?}"),
+		}
+		let decoded: T = codec.decode(&frame)?;
+		assert_eq!(
+			serde_json::to_value(decoded)?,
+			serde_json::to_value(message)?,
+			"{codec:?} changed the message"
+		);
+		Ok(())
+	}
+
+	/// Server messages survive encoding and decoding with every codec.
+	#[test]
+	fn server_event_round_trip() -> Result<(), CodecError> {
+		for codec in Codec::ALL {
+			for event in server_events() {
+				assert_round_trip(codec, &event)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Client messages survive encoding and decoding with every codec.
+	#[test]
+	fn client_message_round_trip() -> Result<(), CodecError> {
+		for codec in Codec::ALL {
+			for message in client_messages() {
+				assert_round_trip(codec, &message)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Text frames are decoded as JSON regardless of the codec.
+	#[test]
+	fn text_frames_decode_as_json() -> Result<(), CodecError> {
+		for codec in Codec::ALL {
+			for message in client_messages() {
+				let frame = Codec::Json.encode(&message)?;
+				let decoded: ClientMessage = codec.decode(&frame)?;
+				assert_eq!(serde_json::to_value(decoded)?, serde_json::to_value(&message)?);
+			}
+		}
+		Ok(())
+	}
+
+	/// Negotiation picks the first supported subprotocol.
+	#[test]
+	fn negotiate() {
+		assert_eq!(Codec::negotiate("buzzer.cbor, buzzer.msgpack"), Some(Codec::Cbor));
+		assert_eq!(Codec::negotiate("chat, buzzer.msgpack"), Some(Codec::MessagePack));
+		assert_eq!(Codec::negotiate("chat"), None);
+	}
+}
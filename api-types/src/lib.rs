@@ -1,12 +1,17 @@
 //! API types
 
+pub mod codec;
 pub mod websocket;
 
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 /// Authentication cookie name.
 pub const AUTH_COOKIE: &str = "user_token";
 
+/// Maximum length of user names in characters
+pub const MAX_NAME_LENGTH: usize = 32;
+
 /// Login information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoginInfo {
@@ -15,10 +20,12 @@ pub struct LoginInfo {
 }
 
 impl LoginInfo {
-	/// Validate the login info.
+	/// Validate the login info. The name must not be blank or longer than
+	/// [`MAX_NAME_LENGTH`], ignoring surrounding whitespace.
 	#[must_use]
 	pub fn is_valid(&self) -> bool {
-		!self.username.is_empty()
+		let name = self.username.trim();
+		!name.is_empty() && name.chars().count() <= MAX_NAME_LENGTH
 	}
 }
 
@@ -27,4 +34,138 @@ impl LoginInfo {
 pub struct LoginResponse {
 	/// Login token
 	pub token: String,
+	/// User ID, which stays the same when logging in again with a valid token
+	pub id: Uuid,
+}
+
+/// Configuration of a buzzer room. Missing fields take the default values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoomSettings {
+	/// Time window in milliseconds in which buzzes are ordered by their
+	/// latency-compensated press time instead of their arrival time. This is
+	/// also the maximum compensation a single buzz can get.
+	pub fairness_window_ms: u64,
+	/// Points awarded when the host marks an answer as correct
+	pub points_correct: i64,
+	/// Points deducted when the host marks an answer as incorrect
+	pub points_incorrect: i64,
+	/// Lockout of players who buzz before the buzzer is armed
+	pub false_start_lockout: Lockout,
+	/// Lockout of players whose answer was marked as incorrect
+	pub wrong_answer_lockout: Lockout,
+}
+
+impl Default for RoomSettings {
+	fn default() -> Self {
+		Self {
+			fairness_window_ms: 250,
+			points_correct: 1,
+			points_incorrect: 0,
+			false_start_lockout: Lockout::Millis(3000),
+			wrong_answer_lockout: Lockout::RestOfQuestion,
+		}
+	}
+}
+
+/// Lockout of a player from buzzing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Lockout {
+	/// No lockout
+	Off,
+	/// Lockout for the given milliseconds
+	Millis(u64),
+	/// Lockout until the next question
+	RestOfQuestion,
+}
+
+/// Request to create a new room
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateRoom {
+	/// Password required to join the room, if any
+	#[serde(default)]
+	pub password: Option<String>,
+	/// Whether the room can only be joined with an invite token
+	#[serde(default)]
+	pub invite_only: bool,
+	/// Whether the room is listed publicly
+	#[serde(default)]
+	pub public: bool,
+	/// Configuration of the room, the server's defaults are used if not set
+	#[serde(default)]
+	pub settings: Option<RoomSettings>,
+}
+
+/// Response to creating a room
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateRoomResponse {
+	/// ID of the new room
+	pub id: Uuid,
+	/// Invite token, allowing to join the room without the password
+	pub invite: String,
+}
+
+/// Access requirements of a room
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomAccessInfo {
+	/// Whether a password is required to join the room
+	pub password: bool,
+	/// Whether an invite token is required to join the room
+	pub invite_only: bool,
+	/// Whether the given password or invite grants access to the room
+	pub granted: bool,
+}
+
+/// Role of a websocket connection in a room
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+	/// Member of the room who can buzz and become host
+	#[default]
+	Player,
+	/// Read-only connection receiving all events, e.g. for a display screen.
+	/// Spectators are not listed as members, cannot buzz and never become
+	/// host.
+	Spectator,
+}
+
+/// Query parameters for joining a room via the websocket
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JoinQuery {
+	/// Room password
+	#[serde(default)]
+	pub password: Option<String>,
+	/// Invite token
+	#[serde(default)]
+	pub invite: Option<String>,
+	/// Role to join the room with
+	#[serde(default)]
+	pub role: Role,
+	/// Resume token of a previous connection to continue its session
+	#[serde(default)]
+	pub resume: Option<Uuid>,
+}
+
+/// Query parameter of the server-sent events fallback of the websocket, which
+/// streams the room's events, and of posting messages to it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct StreamQuery {
+	/// ID of the event stream, chosen randomly by the client
+	pub stream: Uuid,
+}
+
+/// Publicly listed room
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicRoom {
+	/// ID of the room
+	pub id: Uuid,
+	/// Display name of the current host, empty if nobody is in the room
+	pub host: String,
+	/// Number of members currently in the room
+	pub members: usize,
+	/// Whether a password is required to join the room
+	pub password: bool,
+	/// Whether an invite token is required to join the room
+	pub invite_only: bool,
 }
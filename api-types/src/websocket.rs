@@ -1,34 +1,370 @@
 //! Websocket communication types
 
+use std::collections::{BTreeMap, HashMap};
+
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Version of the websocket protocol spoken by this crate
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Oldest version of the websocket protocol that is still supported
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Websocket close code for kicked or banned members
+pub const CLOSE_CODE_KICKED: u16 = 4000;
+/// Websocket close code for closed rooms
+pub const CLOSE_CODE_CLOSED: u16 = 4001;
+/// Websocket close code when the server restarts, the standard "service
+/// restart" code. Clients should reconnect.
+pub const CLOSE_CODE_RESTARTING: u16 = 1012;
+
+/// Protocol handshake. Clients must send it as [`ClientMessage::Hello`] right
+/// after connecting, with the newest protocol version and the capabilities they
+/// support. The server answers with [`ServerMessage::Welcome`], carrying the
+/// negotiated version and capabilities, or closes the connection if the
+/// client's version is not supported anymore.
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hello {
+	/// Protocol version
+	pub version: u32,
+	/// Supported optional protocol features
+	pub capabilities: Vec<Capability>,
+}
+
+impl Hello {
+	/// Hello of the current protocol version with all known capabilities.
+	#[must_use]
+	pub fn current() -> Self {
+		Self { version: PROTOCOL_VERSION, capabilities: Capability::ALL.to_vec() }
+	}
+}
+
+/// Optional feature of the websocket protocol. Messages of features that were
+/// not negotiated are not sent.
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Capability {
+	/// Latency measurements with [`ServerMessage::Ping`] and
+	/// [`ClientMessage::Pong`], used to compensate buzz times
+	Latency,
+	/// Countdown of open rounds with [`ServerMessage::Tick`]
+	Ticks,
+	/// Resuming sessions after disconnects with [`ServerMessage::Resume`]
+	Resume,
+	/// Capability of a newer protocol version, ignored
+	#[serde(other)]
+	Unknown,
+}
+
+impl Capability {
+	/// All capabilities known to this protocol version
+	pub const ALL: [Self; 3] = [Self::Latency, Self::Ticks, Self::Resume];
+}
+
+/// Message from server to client with its sequence number
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerEvent {
+	/// Sequence number of the room's event stream, increasing by one with
+	/// every room event. Messages that are only sent to a single connection,
+	/// like [`ServerMessage::Ping`], and [`ServerMessage::Tick`] carry the
+	/// sequence number of the last room event sent to it. A jump by more than
+	/// one means events were missed, a resync can be requested with
+	/// [`ClientMessage::Resync`].
+	pub seq: u64,
+	/// The message
+	pub message: ServerMessage,
+}
 
 /// Message type for websocket communication from server to client
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ServerMessage {
+	/// Answer to [`ClientMessage::Hello`] with the negotiated protocol version
+	/// and capabilities, sent before any other message
+	Welcome(Hello),
 	/// Current state of the room
 	State(RoomState),
-	/// Who buzzed
-	Buzzed(Option<String>),
+	/// Who buzzed first, i.e. whose turn it is. Announced when the fairness
+	/// window after the first buzz has passed, together with the buzz queue.
+	Buzzed(Option<BuzzEntry>),
+	/// Everyone who buzzed for the current question, in order of buzzing
+	BuzzQueue(Vec<BuzzEntry>),
+	/// Scoreboard by user ID
+	Scores(HashMap<Uuid, Score>),
+	/// Teams of the room by team name
+	Teams(BTreeMap<String, Team>),
+	/// A member was kicked from the room. The kicked member's connection is
+	/// closed afterwards.
+	Kicked {
+		/// User ID of the kicked member
+		id: Uuid,
+		/// Reason given by the host
+		reason: Option<String>,
+	},
+	/// The room was closed. All connections are closed afterwards.
+	Closed,
+	/// The server is restarting. All connections are closed afterwards, but
+	/// the room is kept, so clients should reconnect.
+	Restarting,
+	/// State of the question round changed
+	Round(Round),
+	/// Countdown of the open round, sent every second. Carries the remaining
+	/// milliseconds until the buzzer locks. Not part of the room's event
+	/// sequence.
+	Tick(f64),
+	/// Token to resume this connection's session after a disconnect, by
+	/// passing it as `resume` query parameter when reconnecting
+	Resume(Uuid),
+	/// The server could not handle a message or rejected the connection. Fatal
+	/// errors are followed by closing the connection with the error code's
+	/// [close code](ErrorCode::close_code).
+	Error {
+		/// Kind of error
+		code: ErrorCode,
+		/// Human readable description
+		message: String,
+	},
+	/// Latency measurement request, carrying the server's time in
+	/// milliseconds since the UNIX epoch. Must be answered by
+	/// [`ClientMessage::Pong`].
+	Ping(f64),
 }
 
 /// Message type for websocket communication from client to server
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ClientMessage {
+	/// Protocol handshake, must be the first message of the connection
+	Hello(Hello),
 	/// Press the buzzer
-	Buzz,
+	Buzz {
+		/// Client's local time of pressing the buzzer in milliseconds since the
+		/// UNIX epoch
+		pressed_at: Option<f64>,
+	},
 	/// Free the buzzer
 	FreeBuzzer,
+	/// Arm the buzzer for a new question round, with an optional countdown in
+	/// seconds after which the buzzer locks (host only)
+	OpenRound {
+		/// Seconds until the buzzer locks, None for no time limit
+		countdown_secs: Option<u32>,
+	},
+	/// Lock the buzzer of the current round (host only)
+	LockRound,
+	/// Reveal the answer of the current round (host only)
+	RevealRound,
+	/// End the current round and wait for the next one (host only)
+	ResetRound,
+	/// Reject the current buzzer and pass on to the next one in the queue
+	NextBuzzer,
+	/// Award points to a person (host only). Negative points deduct points.
+	AwardPoints {
+		/// User ID of the person
+		id: Uuid,
+		/// Points to add
+		points: i64,
+	},
+	/// Create a new team (host only)
+	CreateTeam(String),
+	/// Remove a team (host only)
+	RemoveTeam(String),
+	/// Join a team or leave the current team with None. Teams cannot change
+	/// while the buzzer is armed or locked.
+	JoinTeam(Option<String>),
+	/// Put a person into a team or remove them from their team with None (host
+	/// only). Teams cannot change while the buzzer is armed or locked.
+	AssignTeam {
+		/// User ID of the person
+		id: Uuid,
+		/// Name of the team
+		team: Option<String>,
+	},
+	/// Hand the host role to another member (host only)
+	TransferHost(Uuid),
+	/// Kick a member from the room (host only)
+	Kick {
+		/// User ID of the member
+		id: Uuid,
+		/// Reason shown to the kicked member
+		reason: Option<String>,
+	},
+	/// Kick a person and ban them from rejoining the room (host only)
+	Ban {
+		/// User ID of the person
+		id: Uuid,
+		/// Reason shown to the banned person
+		reason: Option<String>,
+	},
+	/// Mark the current buzzer's answer as correct or incorrect (host only).
+	/// This awards or deducts the room's configured points and frees the
+	/// buzzer or passes on to the next buzzer respectively.
+	JudgeAnswer {
+		/// Whether the answer was correct
+		correct: bool,
+	},
+	/// Change the own display name in the room
+	ChangeName(String),
+	/// Request the full room state, e.g. after missing events. Answered with
+	/// [`ServerMessage::State`].
+	Resync,
 	/// Leave
 	Leave,
+	/// Answer to [`ServerMessage::Ping`]
+	Pong {
+		/// Server time that was received in the ping
+		server_time: f64,
+		/// Client's local time when receiving the ping in milliseconds since
+		/// the UNIX epoch
+		client_time: f64,
+	},
+}
+
+/// Kind of an error reported by the server with [`ServerMessage::Error`]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorCode {
+	/// The handshake is missing or the protocol version is not supported
+	/// anymore (fatal)
+	IncompatibleProtocol,
+	/// A message could not be parsed (fatal)
+	MalformedMessage,
+	/// The action is not allowed, e.g. host-only actions or actions of
+	/// spectators
+	Forbidden,
+	/// A member or team name is empty or already taken
+	InvalidName,
+	/// The action does not fit the room, e.g. unknown members or teams
+	InvalidAction,
+	/// Too many messages, the message was dropped. Fatal if the client keeps
+	/// sending messages.
+	RateLimited,
+	/// Error of a newer protocol version
+	#[serde(other)]
+	Unknown,
+}
+
+impl ErrorCode {
+	/// Websocket close code used when the error closes the connection
+	#[must_use]
+	pub const fn close_code(self) -> u16 {
+		match self {
+			Self::IncompatibleProtocol => 4002,
+			Self::MalformedMessage => 4003,
+			Self::Forbidden => 4004,
+			Self::InvalidName => 4005,
+			Self::InvalidAction => 4006,
+			Self::RateLimited => 4007,
+			Self::Unknown => 4999,
+		}
+	}
 }
 
 /// Room state
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RoomState {
-	/// Room members (names)
-	pub members: Vec<String>,
-	/// Host
-	pub host: String,
-	/// Buzzing person
-	pub buzzed: Option<String>,
+	/// Room members
+	pub members: Vec<Member>,
+	/// User ID of the host, None if the room is empty
+	pub host: Option<Uuid>,
+	/// Persons who buzzed, in order of buzzing
+	pub buzz_queue: Vec<BuzzEntry>,
+	/// Scoreboard by user ID
+	pub scores: HashMap<Uuid, Score>,
+	/// Teams by team name
+	pub teams: BTreeMap<String, Team>,
+	/// Current question round
+	pub round: Round,
+	/// Number of connected spectators
+	pub spectators: usize,
+}
+
+/// Member of a room
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Member {
+	/// User ID
+	pub id: Uuid,
+	/// Display name
+	pub name: String,
+	/// Whether the member is connected. Disconnected members keep their seat
+	/// for a grace period to reconnect.
+	pub connected: bool,
+}
+
+/// Scoreboard entry of a person
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Score {
+	/// Last known display name of the person
+	pub name: String,
+	/// Points of the person
+	pub points: i64,
+}
+
+/// Phase of a question round
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoundPhase {
+	/// Waiting for the host to arm the buzzer. Buzzing is a false start.
+	#[default]
+	Idle,
+	/// The buzzer is armed, buzzes are accepted
+	Open,
+	/// The buzzer is locked, by the host or because time ran out. Buzzing is a
+	/// false start for the next question.
+	Locked,
+	/// The answer was revealed. Buzzing is a false start for the next question.
+	Revealed,
+}
+
+/// Question round
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Round {
+	/// Current phase of the round
+	pub phase: RoundPhase,
+	/// Server time when the buzzer locks in milliseconds since the UNIX epoch,
+	/// if the round has a countdown
+	pub deadline: Option<f64>,
+	/// Milliseconds until the buzzer locks at the time of sending, if the round
+	/// has a countdown
+	pub remaining_ms: Option<f64>,
+	/// User IDs of the persons who buzzed before the buzzer was armed
+	pub false_starts: Vec<Uuid>,
+	/// Persons who are locked out from buzzing by user ID, with the remaining
+	/// milliseconds of the lockout at the time of sending. None means they are
+	/// locked out for the rest of the question.
+	pub locked_out: HashMap<Uuid, Option<f64>>,
+}
+
+/// Team of a room
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Team {
+	/// User IDs of the team members
+	pub members: Vec<Uuid>,
+	/// Points of the team
+	pub score: i64,
+}
+
+/// Entry of the buzz queue
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuzzEntry {
+	/// User ID of the person who buzzed
+	pub id: Uuid,
+	/// Name of the person who buzzed
+	pub name: String,
+	/// Team of the person who buzzed
+	pub team: Option<String>,
+	/// Milliseconds between the first buzz and this one, after latency
+	/// compensation
+	pub delta_ms: f64,
+	/// Measured round-trip time of the person's connection in milliseconds,
+	/// if known
+	pub rtt_ms: Option<f64>,
 }
@@ -1,13 +1,21 @@
 //! Build file
 #![allow(clippy::expect_used)]
 
-use std::process::Command;
+use std::{
+	collections::hash_map::DefaultHasher,
+	fmt::Write as _,
+	fs,
+	hash::{Hash, Hasher},
+	path::{Path, PathBuf},
+	process::Command,
+};
 
 fn main() {
 	let frontend_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/frontend");
 
 	println!("cargo:rerun-if-changed={frontend_dir}/src");
 	println!("cargo:rerun-if-changed={frontend_dir}/Cargo.toml");
+	println!("cargo:rerun-if-changed=migrations");
 
 	let build = Command::new("wasm-pack")
 		.args(["build", "--release", "--target", "web"])
@@ -15,4 +23,63 @@ fn main() {
 		.status()
 		.expect("running frontend build");
 	assert!(build.success());
+
+	if std::env::var_os("CARGO_FEATURE_EMBED_FRONTEND").is_some() {
+		embed_frontend(Path::new(frontend_dir));
+	}
+}
+
+/// Generate the table of embedded frontend assets, with their URL path,
+/// content and ETag.
+fn embed_frontend(frontend_dir: &Path) {
+	let mut files = Vec::new();
+	for file in ["index.html", "index.js", "bulma.css"] {
+		let path = frontend_dir.join(file);
+		println!("cargo:rerun-if-changed={}", path.display());
+		files.push((format!("/{file}"), path));
+	}
+	collect_files(&frontend_dir.join("pkg"), "/pkg", &mut files);
+
+	let mut assets = String::from("&[\n");
+	for (url_path, path) in files {
+		let content = fs::read(&path).expect("reading frontend asset");
+		let mut hasher = DefaultHasher::new();
+		content.hash(&mut hasher);
+		let etag = format!("\"{:016x}\"", hasher.finish());
+		writeln!(
+			assets,
+			"\tAsset {{ path: {url_path:?}, content: include_bytes!({:?}), etag: {etag:?} }},",
+			path.display().to_string()
+		)
+		.expect("writing to string");
+	}
+	assets.push_str("]\n");
+
+	let out_dir = std::env::var("OUT_DIR").expect("build script output directory");
+	fs::write(Path::new(&out_dir).join("frontend_assets.rs"), assets)
+		.expect("writing frontend assets");
+}
+
+/// Collect the files in the directory and its subdirectories with their URL
+/// paths, skipping hidden files.
+fn collect_files(dir: &Path, url_path: &str, files: &mut Vec<(String, PathBuf)>) {
+	let mut entries = fs::read_dir(dir)
+		.expect("reading frontend build")
+		.collect::<Result<Vec<_>, _>>()
+		.expect("reading frontend build");
+	// Keep the generated code stable.
+	entries.sort_by_key(fs::DirEntry::file_name);
+	for entry in entries {
+		let name = entry.file_name().to_string_lossy().into_owned();
+		if name.starts_with('.') {
+			continue;
+		}
+		let path = entry.path();
+		let url_path = format!("{url_path}/{name}");
+		if path.is_dir() {
+			collect_files(&path, &url_path, files);
+		} else {
+			files.push((url_path, path));
+		}
+	}
 }
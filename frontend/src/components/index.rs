@@ -1,5 +1,7 @@
 //! Index page component
 
+use api_types::{CreateRoom, CreateRoomResponse, JoinQuery};
+use reqwasm::http::Request;
 use uuid::Uuid;
 use wasm_bindgen::UnwrapThrowExt;
 use yew::{context::ContextHandle, html, Component};
@@ -10,8 +12,16 @@ use crate::routes::{GlobalContext, Routes};
 
 /// Actions the user can trigger
 pub enum Actions {
-	/// Create a new room (join random room)
+	/// Create a new room
 	CreateRoom,
+	/// The room was created, join it
+	RoomCreated(CreateRoomResponse),
+	/// Creating the room failed with the error
+	CreateFailed(String),
+	/// Input value change of the new room's password field
+	PasswordValue(String),
+	/// Toggle whether the new room is invite-only
+	ToggleInviteOnly,
 	/// Join a specific, given room
 	JoinRoom,
 	/// Go to user edit page
@@ -29,6 +39,14 @@ pub struct Index {
 	room_id: Option<Uuid>,
 	/// Additional CSS class of the input field
 	input_class: Option<&'static str>,
+	/// Password of the room to create
+	password: String,
+	/// Whether the room to create is invite-only
+	invite_only: bool,
+	/// Whether the room is being created
+	creating: bool,
+	/// Error of the last attempt to create a room
+	error: Option<String>,
 	/// Global state
 	global_state: GlobalContext,
 	/// Global state context listener
@@ -52,17 +70,84 @@ impl Component for Index {
 			history.push(Routes::UserEdit);
 		}
 
-		Self { room_id: None, input_class: None, global_state, _context_listener: listener }
+		Self {
+			room_id: None,
+			input_class: None,
+			password: String::new(),
+			invite_only: false,
+			creating: false,
+			error: None,
+			global_state,
+			_context_listener: listener,
+		}
 	}
 
 	#[allow(clippy::expect_used)] // used for global history setup
 	fn update(&mut self, ctx: &yew::Context<Self>, msg: Self::Message) -> bool {
 		match msg {
 			Actions::CreateRoom => {
-				let id = Uuid::new_v4();
-				self.room_id = Some(id);
+				let token = match self.global_state.user.as_ref() {
+					Some(user) => user.token.clone(),
+					None => return false,
+				};
+				let create_room = CreateRoom {
+					password: (!self.password.is_empty()).then(|| self.password.clone()),
+					invite_only: self.invite_only,
+					public: false,
+					settings: None,
+				};
+				ctx.link()
+					.callback_future_once(move |create_room: CreateRoom| async move {
+						let response = Request::post("/rooms")
+							.body(
+								serde_json::to_string(&create_room).expect_throw("serialize JSON"),
+							)
+							.header("Content-Type", "application/json")
+							.header("Authorization", &format!("Bearer {token}"))
+							.send()
+							.await;
+						let response = match response {
+							Ok(response) if response.ok() => response,
+							Ok(response) => {
+								let error = response
+									.text()
+									.await
+									.unwrap_or_else(|_| response.status_text());
+								return Actions::CreateFailed(error);
+							}
+							Err(err) => return Actions::CreateFailed(err.to_string()),
+						};
+
+						match response.json().await {
+							Ok(created) => Actions::RoomCreated(created),
+							Err(err) => Actions::CreateFailed(err.to_string()),
+						}
+					})
+					.emit(create_room);
+
+				self.creating = true;
+				self.error = None;
+				true
+			}
+			Actions::RoomCreated(created) => {
 				let history = ctx.link().history().expect("access to history");
-				history.push(Routes::Room { id });
+				let join = JoinQuery { invite: Some(created.invite), ..JoinQuery::default() };
+				history
+					.push_with_query(Routes::Room { id: created.id }, join)
+					.expect_throw("set query of room page");
+				false
+			}
+			Actions::CreateFailed(error) => {
+				self.creating = false;
+				self.error = Some(error);
+				true
+			}
+			Actions::PasswordValue(value) => {
+				self.password = value;
+				false
+			}
+			Actions::ToggleInviteOnly => {
+				self.invite_only = !self.invite_only;
 				false
 			}
 			Actions::JoinRoom => {
@@ -102,9 +187,13 @@ impl Component for Index {
 		let input_change =
 			ctx.link().callback(|event| Actions::InputValue(get_value_from_input_event(event)));
 		let edit_user = ctx.link().callback(|_e| Actions::EditUser);
+		let password_change =
+			ctx.link().callback(|event| Actions::PasswordValue(get_value_from_input_event(event)));
+		let toggle_invite_only = ctx.link().callback(|_e| Actions::ToggleInviteOnly);
 
 		let classes: String =
 			["input"].iter().chain(&self.input_class).flat_map(|s| [*s, " "]).collect();
+		let create_classes = if self.creating { "button is-loading" } else { "button" };
 
 		let user_edit_link = (*self.global_state)
 			.user
@@ -117,11 +206,23 @@ impl Component for Index {
 				{ user_edit_link }
 			</button>
 			<div class="section">
-				<div class="field">
+				<div class="field has-addons">
 					<div class="control">
-						<button class="button" onclick={create_room}>{ "Create Room" }</button>
+						<input class="input" type="password" placeholder="<password (optional)>" oninput={password_change} />
 					</div>
+					<div class="control">
+						<button class={create_classes} onclick={create_room}>{ "Create Room" }</button>
+					</div>
+				</div>
+				<div class="field">
+					<label class="checkbox">
+						<input type="checkbox" onchange={toggle_invite_only} />
+						{ " Invite only" }
+					</label>
 				</div>
+				if let Some(error) = self.error.as_ref() {
+					<div class="notification is-danger is-light">{ error }</div>
+				}
 				<div class="field has-addons">
 					<div class="control">
 						<input class={classes} type="text" placeholder="<uuid>" oninput={input_change} />
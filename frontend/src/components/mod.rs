@@ -6,12 +6,14 @@ pub mod user_edit;
 
 use std::rc::Rc;
 
+use api_types::{JoinQuery, Role};
 pub use index::Index;
 pub use room::Room;
-pub use user_edit::UserEdit;
+pub use user_edit::{UserEdit, UserEditQuery};
+use uuid::Uuid;
 use wasm_bindgen::{JsCast, UnwrapThrowExt};
-use web_sys::HtmlInputElement;
-use yew::{InputEvent, Reducible};
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::{Event, InputEvent, Reducible};
 
 /// Global context data/state
 #[derive(Debug, PartialEq, Eq, Default)]
@@ -23,6 +25,8 @@ pub struct GlobalState {
 /// User data
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct UserData {
+	/// User ID
+	pub id: Uuid,
 	/// Username
 	pub name: String,
 	/// Login token
@@ -53,3 +57,27 @@ fn get_value_from_input_event(event: InputEvent) -> String {
 	let target: HtmlInputElement = event_target.dyn_into().unwrap_throw();
 	target.value()
 }
+
+/// Extract the `<select />`'s value from the change [`Event`].
+fn get_value_from_change_event(event: Event) -> String {
+	let event_target = event.target().unwrap_throw();
+	let target: HtmlSelectElement = event_target.dyn_into().unwrap_throw();
+	target.value()
+}
+
+/// Encode the password, invite, role and resume token to the URL query string
+/// for joining a room.
+fn join_query_string(join: &JoinQuery) -> String {
+	let role = (join.role == Role::Spectator).then_some("spectator");
+	let resume = join.resume.map(|token| token.to_string());
+	[
+		("password", join.password.as_deref()),
+		("invite", join.invite.as_deref()),
+		("role", role),
+		("resume", resume.as_deref()),
+	]
+	.into_iter()
+	.filter_map(|(key, value)| Some(format!("{key}={}", js_sys::encode_uri_component(value?))))
+	.collect::<Vec<_>>()
+	.join("&")
+}
@@ -1,36 +1,155 @@
 //! Room page component
 
+use std::collections::{BTreeMap, HashMap};
+
 use api_types::{
-	websocket::{ClientMessage, RoomState, ServerMessage},
-	AUTH_COOKIE,
+	codec::{Codec, Frame},
+	websocket::{
+		self, BuzzEntry, ClientMessage, ErrorCode, Hello, Member, RoomState, Round, RoundPhase,
+		Score, ServerEvent, ServerMessage, Team,
+	},
+	CreateRoomResponse, JoinQuery, Role, RoomAccessInfo, AUTH_COOKIE,
 };
-use futures::{SinkExt, StreamExt};
-use reqwasm::websocket::{futures::WebSocket, Message};
+use futures::future::{self, Either};
+use gloo_timers::future::TimeoutFuture;
+use reqwasm::http::{Request, Response};
 use tokio::sync::mpsc;
 use uuid::Uuid;
-use wasm_bindgen::UnwrapThrowExt;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue, UnwrapThrowExt};
 use wasm_bindgen_futures::spawn_local;
 use wasm_cookies::CookieOptions;
+use web_sys::{BinaryType, EventSource, MessageEvent, WebSocket};
 use yew::{html, Callback, Component, Html, Properties};
-use yew_router::{history::History, prelude::RouterScopeExt};
+use yew_router::{
+	components::Link,
+	history::{History, Location},
+	prelude::RouterScopeExt,
+};
 
-use super::UserData;
+use super::{
+	get_value_from_change_event, get_value_from_input_event, join_query_string, UserData,
+	UserEditQuery,
+};
 use crate::routes::{GlobalContext, Routes};
 
+/// Delay before the first reconnection attempt in milliseconds, doubled with
+/// every failed attempt.
+const RECONNECT_BASE_DELAY_MS: u32 = 500;
+/// Maximum delay between reconnection attempts in milliseconds.
+const RECONNECT_MAX_DELAY_MS: u32 = 30_000;
+/// Maximum age of queued messages in milliseconds. Older messages are
+/// discarded instead of being sent after reconnecting.
+const OUTBOX_MAX_AGE_MS: f64 = 10_000.0;
+/// Codecs offered to the server for the websocket, in order of preference
+const CODECS: [Codec; 3] = [Codec::MessagePack, Codec::Cbor, Codec::Json];
+/// Number of websocket connections in a row that fail before connecting after
+/// which the connection falls back to server-sent events, e.g. when a proxy
+/// starts blocking websockets.
+const MAX_WS_FAILURES: u32 = 3;
+/// Maximum number of error notifications shown at once, older ones are dropped.
+const MAX_ERRORS: usize = 3;
+
+/// Feedback about the number of messages discarded while disconnected.
+fn discarded_notice(discarded: usize) -> String {
+	format!("{discarded} action(s) could not be sent while disconnected and were discarded.")
+}
+
 /// Actions the user can trigger
 pub enum Actions {
 	/// No action
 	None,
+	/// Check whether the password or invite grants access to the room
+	CheckAccess,
+	/// Result of the access check, connect if access was granted
+	AccessChecked(Result<RoomAccessInfo, String>),
+	/// Get an invite link for the room
+	Invite,
+	/// Show the invite link
+	ShowInvite(Result<CreateRoomResponse, String>),
 	/// Leave the room and go back to index
 	LeaveRoom,
-	/// Press the buzzer
-	Buzz,
+	/// Switch to the large-format display of the room. Going back within the
+	/// reconnection grace period keeps the seat.
+	OpenDisplay,
+	/// Press the buzzer at the given local time
+	Buzz(f64),
 	/// Free the buzzer
 	FreeBuzzer,
-	/// Reconnect the websocket
+	/// Pass on to the next buzzer in the queue
+	NextBuzzer,
+	/// Input value change of the countdown field
+	CountdownInput(String),
+	/// Arm the buzzer for a new round with the countdown from the input field
+	OpenRound,
+	/// Lock the buzzer of the current round
+	LockRound,
+	/// Reveal the answer of the current round
+	RevealRound,
+	/// End the current round
+	ResetRound,
+	/// Award points to a person
+	AwardPoints(Uuid, i64),
+	/// Judge the current buzzer's answer as correct or incorrect
+	JudgeAnswer(bool),
+	/// Input value change of the team name field
+	TeamInput(String),
+	/// Create a team with the name from the input field
+	CreateTeam,
+	/// Remove a team
+	RemoveTeam(String),
+	/// Join a team or leave the current team
+	JoinTeam(Option<String>),
+	/// Hand the host role to another member
+	TransferHost(Uuid),
+	/// Kick a member, asking for the reason
+	Kick(Uuid),
+	/// Kick and ban a member, asking for the reason
+	Ban(Uuid),
+	/// A member was kicked
+	Kicked {
+		/// User ID of the kicked member
+		id: Uuid,
+		/// Reason of the kick
+		reason: Option<String>,
+	},
+	/// The room was closed
+	Closed,
+	/// Reconnect the websocket immediately
 	Reconnect,
-	/// Someone buzzed
-	Buzzed(Option<String>),
+	/// The connection with the given number received its first message
+	Connected(u32),
+	/// The connection with the given number was closed or failed
+	Disconnected(u32),
+	/// Reconnect after the backoff delay of the connection with the given
+	/// number
+	Retry(u32),
+	/// Message received from the server
+	Event(ServerEvent),
+	/// Buzz queue changed
+	BuzzQueue(Vec<BuzzEntry>),
+	/// Scoreboard changed
+	Scores(HashMap<Uuid, Score>),
+	/// Teams changed
+	Teams(BTreeMap<String, Team>),
+	/// Round changed
+	Round(Round),
+	/// Remaining milliseconds of the round's countdown
+	Tick(f64),
+	/// Negotiated protocol version and capabilities
+	Welcome(Hello),
+	/// The server reported an error
+	Error {
+		/// Kind of error
+		code: ErrorCode,
+		/// Description of the error
+		message: String,
+	},
+	/// Dismiss the error notification at the index
+	DismissError(usize),
+	/// Resume token of the connection's session
+	Resume(Uuid),
+	/// Latency measurement request with the server time
+	Ping(f64),
 	/// New room state
 	RoomState(RoomState),
 }
@@ -40,66 +159,885 @@ pub enum Actions {
 pub struct Props {
 	/// Room ID
 	pub id: Uuid,
+	/// Whether to join as spectator and show the large-format display view
+	#[prop_or_default]
+	pub spectator: bool,
+}
+
+/// State of the websocket connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Connection {
+	/// Waiting for the first message of a new connection
+	Connecting,
+	/// Connected to the room
+	Connected,
+	/// The connection was lost, reconnecting after the given seconds
+	Reconnecting(u32),
+}
+
+/// Transport of the connection to the room
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transport {
+	/// Websocket
+	WebSocket,
+	/// Server-sent events with messages posted via HTTP, the fallback for
+	/// networks that block websockets
+	EventStream,
+}
+
+/// Event of a websocket connection
+enum SocketEvent {
+	/// The connection was opened
+	Open,
+	/// Message from the server
+	Frame(Frame),
+	/// The connection was closed by the server or failed
+	Closed,
+}
+
+/// Event of a server-sent events connection
+enum StreamEvent {
+	/// The connection was opened
+	Open,
+	/// Message from the server
+	Message(String),
+	/// The connection was closed by the server or failed
+	Closed,
 }
 
 /// Room component
 pub struct Room {
-	/// Websocket connection sender
-	ws: mpsc::UnboundedSender<Message>,
+	/// Transport of the connection, falls back to server-sent events if the
+	/// websocket cannot connect
+	transport: Transport,
+	/// Websocket connection sender, if connected
+	ws: Option<mpsc::UnboundedSender<ClientMessage>>,
+	/// State of the websocket connection
+	connection: Connection,
+	/// Number of the current connection, to ignore events of replaced
+	/// connections
+	connection_number: u32,
+	/// Number of failed reconnection attempts in a row
+	reconnect_attempts: u32,
+	/// Number of websocket connections in a row that failed before connecting
+	ws_failures: u32,
+	/// Messages waiting to be sent after reconnecting, with the time of
+	/// sending them
+	outbox: Vec<(f64, ClientMessage)>,
+	/// Number of messages discarded since the last reconnection
+	discarded: usize,
+	/// Feedback about messages that were discarded while disconnected
+	notice: Option<String>,
+	/// Errors reported by the server, shown until dismissed
+	errors: Vec<String>,
+	/// Sequence number of the last received room event
+	last_seq: u64,
+	/// Whether a resync was requested and the full room state is awaited
+	resyncing: bool,
+	/// Password and invite to join the room with
+	join: JoinQuery,
 	/// State of the room
 	state: RoomState,
-	/// This person's name
-	my_name: String,
+	/// This user's ID
+	my_id: Uuid,
 	/// This user's login token
 	my_token: String,
+	/// Value of the team name input field
+	team_input: String,
+	/// Value of the countdown input field
+	countdown_input: String,
+	/// Reason why this user was kicked, if they were
+	kicked: Option<String>,
+	/// Reason why this user could not join the room
+	join_error: Option<String>,
 }
 
 impl Room {
-	/// Connect to the room's websocket
+	/// Connect to the room's websocket. `on_connected` is emitted on the first
+	/// received message, `on_disconnected` when the connection closes or fails.
+	/// The codec is negotiated by subprotocol, preferring binary ones. Dropping
+	/// the returned sender closes the connection.
 	pub fn connect_ws(
 		room_id: Uuid,
 		token: &str,
-		on_message: Callback<Message>,
-	) -> mpsc::UnboundedSender<Message> {
+		join: &JoinQuery,
+		on_event: Callback<ServerEvent>,
+		on_connected: Callback<()>,
+		on_disconnected: Callback<()>,
+	) -> mpsc::UnboundedSender<ClientMessage> {
 		let location = web_sys::window().expect_throw("access to window").location();
 		let proto = if location.protocol().ok().as_deref() == Some("https") { "wss" } else { "ws" };
 		let host = location.host().expect_throw("access to window.location.host");
-		let path = format!("{proto}://{host}/{}/ws", room_id);
+		let path = format!("{proto}://{host}/{}/ws?{}", room_id, join_query_string(join));
 
 		let options = CookieOptions::default().with_path("/");
 		wasm_cookies::set(AUTH_COOKIE, token, &options);
 
-		let ws = WebSocket::open(&path).expect_throw("connecting to room websocket");
-		let (mut sender, mut receiver) = ws.split();
 		let (channel_sender, mut channel_receiver) = mpsc::unbounded_channel();
+		let protocols: js_sys::Array =
+			CODECS.iter().map(|codec| JsValue::from_str(codec.subprotocol())).collect();
+		let ws = match WebSocket::new_with_str_sequence(&path, &protocols) {
+			Ok(ws) => ws,
+			Err(err) => {
+				log::warn!("Could not open websocket: {err:?}");
+				on_disconnected.emit(());
+				return channel_sender;
+			}
+		};
+		// Array buffers can be read synchronously, keeping the messages in order.
+		ws.set_binary_type(BinaryType::Arraybuffer);
+
+		let (event_sender, mut events) = mpsc::unbounded_channel();
+		let on_open: Closure<dyn FnMut()> = {
+			let event_sender = event_sender.clone();
+			Closure::wrap(Box::new(move || {
+				event_sender.send(SocketEvent::Open).ok();
+			}))
+		};
+		let on_frame: Closure<dyn FnMut(MessageEvent)> = {
+			let event_sender = event_sender.clone();
+			Closure::wrap(Box::new(move |event: MessageEvent| {
+				let data = event.data();
+				let frame = match data.as_string() {
+					Some(text) => Frame::Text(text),
+					None => Frame::Binary(js_sys::Uint8Array::new(&data).to_vec()),
+				};
+				event_sender.send(SocketEvent::Frame(frame)).ok();
+			}))
+		};
+		// Errors are followed by the close event.
+		let on_close: Closure<dyn FnMut()> = Closure::wrap(Box::new(move || {
+			event_sender.send(SocketEvent::Closed).ok();
+		}));
+		ws.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+		ws.set_onmessage(Some(on_frame.as_ref().unchecked_ref()));
+		ws.set_onclose(Some(on_close.as_ref().unchecked_ref()));
 
 		spawn_local(async move {
-			while let Some(msg) = receiver.next().await {
-				on_message.emit(msg.expect_throw("receiving websocket message"));
+			let mut codec = Codec::default();
+			let mut connected = false;
+			loop {
+				let event = match future::select(
+					Box::pin(events.recv()),
+					Box::pin(channel_receiver.recv()),
+				)
+				.await
+				{
+					Either::Left((incoming, _)) => Either::Left(incoming),
+					Either::Right((outgoing, _)) => Either::Right(outgoing),
+				};
+				match event {
+					Either::Left(Some(SocketEvent::Open)) => {
+						codec = Codec::negotiate(&ws.protocol()).unwrap_or_default();
+						log::debug!("Websocket speaks {codec:?}.");
+						let hello = ClientMessage::Hello(Hello::current());
+						if let Err(err) = Self::send_frame(&ws, codec, &hello) {
+							log::info!("Could not send websocket hello: {err:?}");
+							break;
+						}
+					}
+					Either::Left(Some(SocketEvent::Frame(frame))) => {
+						if !connected {
+							connected = true;
+							on_connected.emit(());
+						}
+						match codec.decode(&frame) {
+							Ok(event) => on_event.emit(event),
+							Err(err) => log::warn!("Could not decode websocket message: {err}"),
+						}
+					}
+					Either::Right(Some(msg)) => {
+						if let Err(err) = Self::send_frame(&ws, codec, &msg) {
+							log::info!("Could not send websocket message: {err:?}");
+							break;
+						}
+					}
+					// Closed by the server or replaced by a new connection.
+					Either::Left(Some(SocketEvent::Closed) | None) | Either::Right(None) => break,
+				}
 			}
+			ws.close().ok();
+			drop((on_open, on_frame, on_close));
+			on_disconnected.emit(());
 		});
 
+		channel_sender
+	}
+
+	/// Encode the message with the codec and send it via the websocket.
+	fn send_frame(ws: &WebSocket, codec: Codec, msg: &ClientMessage) -> Result<(), JsValue> {
+		match codec.encode(msg).expect_throw("encoding message") {
+			Frame::Text(text) => ws.send_with_str(&text),
+			Frame::Binary(data) => ws.send_with_u8_array(&data),
+		}
+	}
+
+	/// Connect to the room's server-sent events, the fallback for networks that
+	/// block websockets. Messages to the server are posted one after another.
+	/// Returns the sender for messages to the server.
+	pub fn connect_sse(
+		room_id: Uuid,
+		token: &str,
+		join: &JoinQuery,
+		on_event: Callback<ServerEvent>,
+		on_connected: Callback<()>,
+		on_disconnected: Callback<()>,
+	) -> mpsc::UnboundedSender<ClientMessage> {
+		let stream = Uuid::new_v4();
+		let mut query = join_query_string(join);
+		if !query.is_empty() {
+			query.push('&');
+		}
+		query.push_str(&format!("stream={stream}"));
+		let messages_url = format!("/{room_id}/messages?stream={stream}");
+
+		let options = CookieOptions::default().with_path("/");
+		wasm_cookies::set(AUTH_COOKIE, token, &options);
+
+		let (channel_sender, mut channel_receiver) = mpsc::unbounded_channel();
+		let event_source = match EventSource::new(&format!("/{room_id}/events?{query}")) {
+			Ok(event_source) => event_source,
+			Err(err) => {
+				log::warn!("Could not open event stream: {err:?}");
+				on_disconnected.emit(());
+				return channel_sender;
+			}
+		};
+
+		let (event_sender, mut events) = mpsc::unbounded_channel();
+		let on_open: Closure<dyn FnMut()> = {
+			let event_sender = event_sender.clone();
+			Closure::wrap(Box::new(move || {
+				event_sender.send(StreamEvent::Open).ok();
+			}))
+		};
+		let on_data: Closure<dyn FnMut(MessageEvent)> = {
+			let event_sender = event_sender.clone();
+			Closure::wrap(Box::new(move |event: MessageEvent| {
+				if let Some(data) = event.data().as_string() {
+					event_sender.send(StreamEvent::Message(data)).ok();
+				}
+			}))
+		};
+		// The server's `close` event and errors both end the connection, the
+		// event source's own reconnecting is replaced by the room's.
+		let on_close: Closure<dyn FnMut()> = Closure::wrap(Box::new(move || {
+			event_sender.send(StreamEvent::Closed).ok();
+		}));
+		event_source.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+		event_source.set_onmessage(Some(on_data.as_ref().unchecked_ref()));
+		event_source.set_onerror(Some(on_close.as_ref().unchecked_ref()));
+		event_source
+			.add_event_listener_with_callback("close", on_close.as_ref().unchecked_ref())
+			.ok();
+
 		spawn_local(async move {
-			while let Some(msg) = channel_receiver.recv().await {
-				sender.send(msg).await.expect_throw("sending websocket message");
+			let mut connected = false;
+			loop {
+				let event = match future::select(
+					Box::pin(events.recv()),
+					Box::pin(channel_receiver.recv()),
+				)
+				.await
+				{
+					Either::Left((incoming, _)) => Either::Left(incoming),
+					Either::Right((outgoing, _)) => Either::Right(outgoing),
+				};
+				match event {
+					Either::Left(Some(StreamEvent::Open)) => {
+						let hello = ClientMessage::Hello(Hello::current());
+						if !Self::post_message(&messages_url, &hello).await {
+							break;
+						}
+					}
+					Either::Left(Some(StreamEvent::Message(data))) => {
+						if !connected {
+							connected = true;
+							on_connected.emit(());
+						}
+						match serde_json::from_str(&data) {
+							Ok(event) => on_event.emit(event),
+							Err(err) => log::warn!("Could not decode event stream message: {err}"),
+						}
+					}
+					Either::Right(Some(msg)) => {
+						if !Self::post_message(&messages_url, &msg).await {
+							break;
+						}
+					}
+					// Closed by the server or replaced by a new connection.
+					Either::Left(Some(StreamEvent::Closed) | None) | Either::Right(None) => break,
+				}
 			}
+			event_source.close();
+			drop((on_open, on_data, on_close));
+			on_disconnected.emit(());
 		});
 
 		channel_sender
 	}
 
-	/// What to do on a websocket message
-	fn on_message(msg: Message) -> Actions {
+	/// Post a message to the room's event stream. Returns whether the server
+	/// accepted it.
+	async fn post_message(url: &str, msg: &ClientMessage) -> bool {
+		let body = serde_json::to_string(msg).expect_throw("encoding message");
+		let response =
+			Request::post(url).header("Content-Type", "application/json").body(body).send().await;
+		match response {
+			Ok(response) if response.ok() => true,
+			Ok(response) => {
+				log::info!("Could not post message: {}", response.status_text());
+				false
+			}
+			Err(err) => {
+				log::info!("Could not post message: {err}");
+				false
+			}
+		}
+	}
+
+	/// Render the connection status and feedback about discarded messages.
+	fn view_connection(&self) -> Html {
+		let status = match self.connection {
+			Connection::Connecting => {
+				html! { <span class="tag is-warning">{ "Connecting…" }</span> }
+			}
+			Connection::Connected => html! { <span class="tag is-success">{ "Connected" }</span> },
+			Connection::Reconnecting(secs) => html! {
+				<span class="tag is-danger">{ format!("Disconnected, retrying in {secs} s") }</span>
+			},
+		};
+		html! {
+			<>
+			<p>{ status }</p>
+			if let Some(notice) = self.notice.as_ref() {
+				<div class="notification is-warning">{ notice }</div>
+			}
+			</>
+		}
+	}
+
+	/// Render the dismissable notifications of errors reported by the server.
+	fn view_errors(&self, ctx: &yew::Context<Self>) -> Html {
+		self.errors
+			.iter()
+			.enumerate()
+			.map(|(index, error)| {
+				let dismiss = ctx.link().callback(move |_| Actions::DismissError(index));
+				html! {
+					<div class="notification is-danger is-light">
+						<button class="delete" onclick={dismiss}></button>
+						{ error }
+					</div>
+				}
+			})
+			.collect()
+	}
+
+	/// Render an error notification with a link back to the index page.
+	fn view_error(title: &str, reason: &str) -> Html {
+		html! {
+			<div class="section">
+				<div class="notification is-danger">
+					<p><strong>{ title }</strong></p>
+					<p>{ reason }</p>
+				</div>
+				<Link<Routes> to={Routes::Index} classes="button">{ "Back" }</Link<Routes>>
+			</div>
+		}
+	}
+
+	/// Whether this user is the host of the room
+	fn is_host(&self) -> bool {
+		self.state.host == Some(self.my_id)
+	}
+
+	/// Get the display name of a person in the room or on the scoreboard.
+	fn name_of(&self, id: Uuid) -> &str {
+		self.state
+			.members
+			.iter()
+			.find(|member| member.id == id)
+			.map(|member| member.name.as_str())
+			.or_else(|| self.state.scores.get(&id).map(|score| score.name.as_str()))
+			.unwrap_or("?")
+	}
+
+	/// Get the points of a person.
+	fn points_of(&self, id: Uuid) -> i64 {
+		self.state.scores.get(&id).map(|score| score.points).unwrap_or_default()
+	}
+
+	/// Render the large-format display view for spectators, showing the
+	/// round, the current buzzer and the scoreboard.
+	fn view_display(&self) -> Html {
+		let round = &self.state.round;
+		let headline = match (round.phase, self.state.buzz_queue.first()) {
+			(RoundPhase::Idle, _) => "Get ready…".to_owned(),
+			(_, Some(buzzed)) => match buzzed.team.as_ref() {
+				Some(team) => format!("{} ({team})", buzzed.name),
+				None => buzzed.name.clone(),
+			},
+			(RoundPhase::Open, None) => "Buzz now!".to_owned(),
+			(RoundPhase::Locked, None) => "Time is up!".to_owned(),
+			(RoundPhase::Revealed, None) => "Answer revealed".to_owned(),
+		};
+		let countdown = round
+			.remaining_ms
+			.filter(|_| round.phase == RoundPhase::Open)
+			.map(|remaining| format!("{:.0}", (remaining / 1000.0).ceil()))
+			.unwrap_or_default();
+
+		let mut scoreboard: Vec<(&String, i64)> = if self.state.teams.is_empty() {
+			self.state
+				.members
+				.iter()
+				.map(|member| (&member.name, self.points_of(member.id)))
+				.collect()
+		} else {
+			self.state.teams.iter().map(|(name, team)| (name, team.score)).collect()
+		};
+		scoreboard.sort_by(|(name_a, score_a), (name_b, score_b)| {
+			score_b.cmp(score_a).then_with(|| name_a.cmp(name_b))
+		});
+		let scoreboard = scoreboard
+			.into_iter()
+			.map(|(name, score)| html! { <tr><td>{ name }</td><td>{ score }</td></tr> })
+			.collect::<Html>();
+
+		html! {
+			<section class="hero is-fullheight">
+				<div class="hero-body">
+					<div class="container has-text-centered">
+						<p class="title is-1">{ headline }</p>
+						<p class="subtitle is-1">{ countdown }</p>
+						if self.connection != Connection::Connected {
+							{ self.view_connection() }
+						}
+						<table class="table is-fullwidth is-size-3">
+							<tbody>{ scoreboard }</tbody>
+						</table>
+					</div>
+				</div>
+			</section>
+		}
+	}
+
+	/// Render the round's phase, countdown and false starts and, for the host,
+	/// the round controls.
+	fn view_round(&self, ctx: &yew::Context<Self>) -> Html {
+		let round = &self.state.round;
+		let phase = match round.phase {
+			RoundPhase::Idle => "Waiting for the host to arm the buzzer.",
+			RoundPhase::Open => "The buzzer is open!",
+			RoundPhase::Locked => "The buzzer is locked.",
+			RoundPhase::Revealed => "The answer was revealed.",
+		};
+		let countdown = round
+			.remaining_ms
+			.filter(|_| round.phase == RoundPhase::Open)
+			.map(|remaining| format!(" {:.0} s left", (remaining / 1000.0).ceil()))
+			.unwrap_or_default();
+
+		let false_starts =
+			round.false_starts.iter().map(|&id| self.name_of(id)).collect::<Vec<_>>().join(", ");
+
+		let countdown_input =
+			ctx.link().callback(|event| Actions::CountdownInput(get_value_from_input_event(event)));
+		let open_round = ctx.link().callback(|_e| Actions::OpenRound);
+		let lock_round = ctx.link().callback(|_e| Actions::LockRound);
+		let reveal_round = ctx.link().callback(|_e| Actions::RevealRound);
+		let reset_round = ctx.link().callback(|_e| Actions::ResetRound);
+
+		html! {
+			<>
+			<p><strong>{ phase }</strong>{ countdown }</p>
+			if !round.false_starts.is_empty() {
+				<p>{ "False starts: " }{ false_starts }</p>
+			}
+			if self.is_host() {
+				<form class="field has-addons" onsubmit={open_round} action="javascript:void(0);">
+					<div class="control">
+						<input class="input" type="number" min="1" placeholder="<seconds>" value={self.countdown_input.clone()} oninput={countdown_input} />
+					</div>
+					<div class="control">
+						<input type="submit" class="button is-success" value="Open Buzzer" />
+					</div>
+				</form>
+				<div class="buttons">
+					<button class="button" onclick={lock_round}>{ "Lock" }</button>
+					<button class="button" onclick={reveal_round}>{ "Reveal" }</button>
+					<button class="button" onclick={reset_round}>{ "Next Question" }</button>
+				</div>
+			}
+			</>
+		}
+	}
+
+	/// Render the team picker and, for the host, the team management.
+	#[allow(clippy::if_then_some_else_none)] // generated by yew's boolean attributes
+	fn view_teams_controls(&self, ctx: &yew::Context<Self>) -> Html {
+		let my_team = self
+			.state
+			.teams
+			.iter()
+			.find(|(_, team)| team.members.contains(&self.my_id))
+			.map(|(name, _)| name);
+		let join_team = ctx.link().callback(|event| {
+			let team = get_value_from_change_event(event);
+			Actions::JoinTeam((!team.is_empty()).then_some(team))
+		});
+		let team_options = self
+			.state
+			.teams
+			.keys()
+			.map(|name| {
+				html! {
+					<option value={name.clone()} selected={my_team == Some(name)}>{ name }</option>
+				}
+			})
+			.collect::<Html>();
+
+		let team_input =
+			ctx.link().callback(|event| Actions::TeamInput(get_value_from_input_event(event)));
+		let create_team = ctx.link().callback(|_e| Actions::CreateTeam);
+
+		html! {
+			<>
+			if !self.state.teams.is_empty() {
+				<div class="field">
+					<div class="control">
+						<div class="select">
+							<select onchange={join_team}>
+								<option value="" selected={my_team.is_none()}>{ "No team" }</option>
+								{ team_options }
+							</select>
+						</div>
+					</div>
+				</div>
+			}
+			if self.is_host() {
+				<form class="field has-addons" onsubmit={create_team} action="javascript:void(0);">
+					<div class="control">
+						<input class="input" type="text" placeholder="<team>" value={self.team_input.clone()} oninput={team_input} />
+					</div>
+					<div class="control">
+						<input type="submit" class="button" value="Create Team" />
+					</div>
+				</form>
+			}
+			</>
+		}
+	}
+
+	/// Render the members table, grouped by teams.
+	fn view_members(&self, ctx: &yew::Context<Self>) -> Html {
+		let teams = self
+			.state
+			.teams
+			.iter()
+			.map(|(name, team)| {
+				let remove_team = {
+					let name = name.clone();
+					ctx.link().callback(move |_e| Actions::RemoveTeam(name.clone()))
+				};
+				let members = team
+					.members
+					.iter()
+					.filter_map(|id| self.state.members.iter().find(|member| member.id == *id))
+					.map(|member| self.view_member(ctx, member));
+				html! {
+					<>
+					<tr>
+						<th>{ name }</th>
+						<th>{ team.score }</th>
+						if self.is_host() {
+							<th>
+								<button class="button is-small" onclick={remove_team}>{ "Remove" }</button>
+							</th>
+						}
+					</tr>
+					{ for members }
+					</>
+				}
+			})
+			.collect::<Html>();
+
+		let teamless = self
+			.state
+			.members
+			.iter()
+			.filter(|member| {
+				!self.state.teams.values().any(|team| team.members.contains(&member.id))
+			})
+			.map(|member| self.view_member(ctx, member))
+			.collect::<Html>();
+
+		html! {
+			<table>
+				<thead><tr>
+					<td>{ "Room Members" }</td>
+					<td>{ "Points" }</td>
+				</tr></thead>
+				{ teams }
+				if !self.state.teams.is_empty() {
+					<tr><th>{ "No team" }</th><th></th></tr>
+				}
+				{ teamless }
+			</table>
+		}
+	}
+
+	/// Render a row of the members table.
+	fn view_member(&self, ctx: &yew::Context<Self>, member: &Member) -> Html {
+		let id = member.id;
+		let points = self.points_of(id);
+		let award = ctx.link().callback(move |_e| Actions::AwardPoints(id, 1));
+		let deduct = ctx.link().callback(move |_e| Actions::AwardPoints(id, -1));
+		let make_host = ctx.link().callback(move |_e| Actions::TransferHost(id));
+		let kick = ctx.link().callback(move |_e| Actions::Kick(id));
+		let ban = ctx.link().callback(move |_e| Actions::Ban(id));
+		html! {
+			<tr>
+				<td>
+					if id == self.my_id {
+						<strong>{ &member.name }</strong>
+					} else {
+						{ &member.name }
+					}
+					if !member.connected {
+						<span class="has-text-grey">{ " (disconnected)" }</span>
+					}
+				</td>
+				<td>{ points }</td>
+				if self.is_host() {
+					<td>
+						<button class="button is-small" onclick={award}>{ "+" }</button>
+						<button class="button is-small" onclick={deduct}>{ "-" }</button>
+						if id != self.my_id {
+							<button class="button is-small" onclick={make_host}>{ "Make Host" }</button>
+							<button class="button is-small" onclick={kick}>{ "Kick" }</button>
+							<button class="button is-small is-danger" onclick={ban}>{ "Ban" }</button>
+						}
+					</td>
+				}
+			</tr>
+		}
+	}
+
+	/// Ask the user for a reason for a moderation action or a password. None
+	/// means the action was cancelled.
+	fn ask_reason(message: &str) -> Option<String> {
+		web_sys::window()?.prompt_with_message(message).ok()?
+	}
+
+	/// Send an authenticated GET request to the server and return the
+	/// response if it was successful, otherwise the error text.
+	async fn get(url: &str, token: &str) -> Result<Response, String> {
+		let response = Request::get(url)
+			.header("Authorization", &format!("Bearer {token}"))
+			.send()
+			.await
+			.map_err(|err| err.to_string())?;
+		if response.ok() {
+			Ok(response)
+		} else {
+			Err(response.text().await.unwrap_or_else(|_| response.status_text()))
+		}
+	}
+
+	/// Check whether the password or invite grants access to the room.
+	fn check_access(&self, ctx: &yew::Context<Self>) {
+		let url = format!("/rooms/{}/access?{}", ctx.props().id, join_query_string(&self.join));
+		let token = self.my_token.clone();
+		ctx.link().send_future(async move {
+			let access = match Self::get(&url, &token).await {
+				Ok(response) => response.json().await.map_err(|err| err.to_string()),
+				Err(err) => Err(err),
+			};
+			Actions::AccessChecked(access)
+		});
+	}
+
+	/// Connect if access to the room was granted, otherwise ask for the
+	/// password or show why the room cannot be joined.
+	#[allow(clippy::expect_used)] // used for global history setup
+	fn on_access_checked(&mut self, ctx: &yew::Context<Self>, access: RoomAccessInfo) {
+		if access.granted {
+			self.connect(ctx);
+		} else if access.invite_only {
+			self.join_error = Some("This room can only be joined with an invite.".to_owned());
+		} else {
+			let message = if self.join.password.is_some() {
+				"Wrong password, try again:"
+			} else {
+				"This room requires a password:"
+			};
+			if let Some(password) = Self::ask_reason(message) {
+				self.join.password = Some(password);
+				self.check_access(ctx);
+			} else {
+				let history = ctx.link().history().expect("access to history");
+				history.push(Routes::Index);
+			}
+		}
+	}
+
+	/// Show the invite link so it can be copied, or the error retrieving it.
+	fn show_invite(invite: Result<CreateRoomResponse, String>) {
+		let window = web_sys::window().expect_throw("access to window");
+		let result = match invite {
+			Ok(invite) => {
+				let origin = window.location().origin().unwrap_or_default();
+				let join = JoinQuery { invite: Some(invite.invite), ..JoinQuery::default() };
+				let link = format!("{origin}/{}?{}", invite.id, join_query_string(&join));
+				window.prompt_with_message_and_default("Invite link:", &link).map(drop)
+			}
+			Err(err) => window.alert_with_message(&format!("Could not create invite: {err}")),
+		};
+		result.expect_throw("showing invite");
+	}
+
+	/// (Re-)connect to the room's websocket, replacing the current connection.
+	fn connect(&mut self, ctx: &yew::Context<Self>) {
+		self.connection_number = self.connection_number.wrapping_add(1);
+		self.connection = Connection::Connecting;
+		self.resyncing = false;
+
+		let number = self.connection_number;
+		let on_event = ctx.link().callback(Actions::Event);
+		let on_connected = ctx.link().callback(move |()| Actions::Connected(number));
+		let on_disconnected = ctx.link().callback(move |()| Actions::Disconnected(number));
+		let connect = match self.transport {
+			Transport::WebSocket => Room::connect_ws,
+			Transport::EventStream => Room::connect_sse,
+		};
+		self.ws = Some(connect(
+			ctx.props().id,
+			&self.my_token,
+			&self.join,
+			on_event,
+			on_connected,
+			on_disconnected,
+		));
+	}
+
+	/// Schedule reconnecting with exponential backoff after the connection was
+	/// lost.
+	fn schedule_reconnect(&mut self, ctx: &yew::Context<Self>) {
+		let delay = RECONNECT_BASE_DELAY_MS
+			.saturating_mul(2_u32.saturating_pow(self.reconnect_attempts))
+			.min(RECONNECT_MAX_DELAY_MS);
+		self.reconnect_attempts = self.reconnect_attempts.saturating_add(1);
+		self.connection = Connection::Reconnecting(delay.div_ceil(1000));
+
+		let number = self.connection_number;
+		ctx.link().send_future(async move {
+			TimeoutFuture::new(delay).await;
+			Actions::Retry(number)
+		});
+	}
+
+	/// Send the queued messages after reconnecting. Messages that are too old
+	/// are discarded with feedback to the user.
+	fn flush_outbox(&mut self) {
+		let now = js_sys::Date::now();
+		let (fresh, stale): (Vec<_>, Vec<_>) = std::mem::take(&mut self.outbox)
+			.into_iter()
+			.partition(|(time, _)| now - time <= OUTBOX_MAX_AGE_MS);
+		self.discarded += stale.len();
+		self.notice = (self.discarded > 0).then(|| discarded_notice(self.discarded));
+		self.discarded = 0;
+		for (_, msg) in fresh {
+			self.send(msg);
+		}
+	}
+
+	/// Send a message to the server via the websocket. While disconnected,
+	/// actions independent of the current round are queued to be sent after
+	/// reconnecting, other actions are discarded with feedback to the user.
+	fn send(&mut self, mut msg: ClientMessage) {
+		if let (Some(ws), Connection::Connected) = (self.ws.as_ref(), self.connection) {
+			match ws.send(msg) {
+				Ok(()) => return,
+				Err(mpsc::error::SendError(unsent)) => msg = unsent,
+			}
+		}
 		match msg {
-			Message::Text(msg) => {
-				let event: ServerMessage =
-					serde_json::from_str(&msg).expect_throw("deserializing message");
-				match event {
-					ServerMessage::State(state) => Actions::RoomState(state),
-					ServerMessage::Buzzed(buzzed) => Actions::Buzzed(buzzed),
+			ClientMessage::CreateTeam(_)
+			| ClientMessage::RemoveTeam(_)
+			| ClientMessage::JoinTeam(_)
+			| ClientMessage::AssignTeam { .. }
+			| ClientMessage::TransferHost(_)
+			| ClientMessage::Kick { .. }
+			| ClientMessage::Ban { .. }
+			| ClientMessage::ChangeName(_)
+			| ClientMessage::Leave => self.outbox.push((js_sys::Date::now(), msg)),
+			// The round might have moved on until reconnecting.
+			ClientMessage::Buzz { .. }
+			| ClientMessage::FreeBuzzer
+			| ClientMessage::OpenRound { .. }
+			| ClientMessage::LockRound
+			| ClientMessage::RevealRound
+			| ClientMessage::ResetRound
+			| ClientMessage::NextBuzzer
+			| ClientMessage::AwardPoints { .. }
+			| ClientMessage::JudgeAnswer { .. } => {
+				self.discarded += 1;
+				self.notice = Some(discarded_notice(self.discarded));
+			}
+			// Only valid for the connection they belong to.
+			ClientMessage::Hello(_) | ClientMessage::Resync | ClientMessage::Pong { .. } => {}
+		}
+	}
+
+	/// Track the sequence number of the event and request a resync if events
+	/// were missed. Returns whether the event should be applied, room events
+	/// that are included in the last received full state are skipped.
+	fn track_sequence(&mut self, event: &ServerEvent) -> bool {
+		match event.message {
+			ServerMessage::State(_) => {
+				self.resyncing = false;
+				self.last_seq = event.seq;
+				true
+			}
+			// Only sent to this connection or not part of the room's events.
+			ServerMessage::Welcome(_)
+			| ServerMessage::Error { .. }
+			| ServerMessage::Ping(_)
+			| ServerMessage::Tick(_)
+			| ServerMessage::Resume(_) => true,
+			_ if event.seq <= self.last_seq => false,
+			_ => {
+				if event.seq > self.last_seq.saturating_add(1) && !self.resyncing {
+					self.resyncing = true;
+					self.send(ClientMessage::Resync);
 				}
+				self.last_seq = event.seq;
+				true
 			}
-			_ => Actions::None,
+		}
+	}
+
+	/// What to do on a message from the server
+	fn on_event(message: ServerMessage) -> Actions {
+		match message {
+			ServerMessage::State(state) => Actions::RoomState(state),
+			// The buzz queue update contains this information as well.
+			ServerMessage::Buzzed(_) => Actions::None,
+			ServerMessage::BuzzQueue(queue) => Actions::BuzzQueue(queue),
+			ServerMessage::Scores(scores) => Actions::Scores(scores),
+			ServerMessage::Teams(teams) => Actions::Teams(teams),
+			ServerMessage::Kicked { id, reason } => Actions::Kicked { id, reason },
+			ServerMessage::Closed => Actions::Closed,
+			// The connection is closed afterwards and reconnects to the restarted server.
+			ServerMessage::Restarting => Actions::None,
+			ServerMessage::Round(round) => Actions::Round(round),
+			ServerMessage::Tick(remaining) => Actions::Tick(remaining),
+			ServerMessage::Resume(token) => Actions::Resume(token),
+			ServerMessage::Welcome(hello) => Actions::Welcome(hello),
+			ServerMessage::Error { code, message } => Actions::Error { code, message },
+			ServerMessage::Ping(server_time) => Actions::Ping(server_time),
 		}
 	}
 }
@@ -118,116 +1056,381 @@ impl Component for Room {
 		let user = if let Some(user) = global_state.user.as_ref() {
 			user.clone()
 		} else {
-			ctx.link().history().expect("accessing history").push(Routes::UserEdit);
+			UserEditQuery::edit_and_return(&ctx.link().history().expect("accessing history"));
 			UserData::default()
 		};
 
-		let on_message = ctx.link().callback(Room::on_message);
-		let ws = Room::connect_ws(ctx.props().id, &user.token, on_message);
-		Self { ws, state: RoomState::default(), my_name: user.name, my_token: user.token }
+		let mut join = ctx
+			.link()
+			.location()
+			.and_then(|location| location.query::<JoinQuery>().ok())
+			.unwrap_or_default();
+		join.role = if ctx.props().spectator { Role::Spectator } else { Role::Player };
+		ctx.link().send_message(Actions::CheckAccess);
+		Self {
+			transport: Transport::WebSocket,
+			ws: None,
+			connection: Connection::Connecting,
+			connection_number: 0,
+			reconnect_attempts: 0,
+			ws_failures: 0,
+			outbox: Vec::new(),
+			discarded: 0,
+			notice: None,
+			errors: Vec::new(),
+			last_seq: 0,
+			resyncing: false,
+			join,
+			state: RoomState::default(),
+			my_id: user.id,
+			my_token: user.token,
+			team_input: String::new(),
+			countdown_input: String::new(),
+			kicked: None,
+			join_error: None,
+		}
 	}
 
 	#[allow(clippy::expect_used)] // used for global history setup
+	#[allow(clippy::too_many_lines)] // one short arm per action
 	fn update(&mut self, ctx: &yew::Context<Self>, msg: Self::Message) -> bool {
 		match msg {
 			Actions::None => false,
+			Actions::CheckAccess => {
+				self.check_access(ctx);
+				false
+			}
+			Actions::AccessChecked(Err(err)) => {
+				self.join_error = Some(err);
+				true
+			}
+			Actions::AccessChecked(Ok(access)) => {
+				self.on_access_checked(ctx, access);
+				true
+			}
+			Actions::Invite => {
+				let url = format!("/rooms/{}/invite", ctx.props().id);
+				let token = self.my_token.clone();
+				ctx.link().send_future(async move {
+					let invite = match Self::get(&url, &token).await {
+						Ok(response) => response.json().await.map_err(|err| err.to_string()),
+						Err(err) => Err(err),
+					};
+					Actions::ShowInvite(invite)
+				});
+				false
+			}
+			Actions::ShowInvite(invite) => {
+				Self::show_invite(invite);
+				false
+			}
 			Actions::LeaveRoom => {
-				let msg = serde_json::to_string(&ClientMessage::Leave)
-					.expect_throw("serializing message");
-				self.ws.send(Message::Text(msg)).expect_throw("sending message");
+				self.send(ClientMessage::Leave);
 				let history = ctx.link().history().expect("access to history");
 				history.push(Routes::Index);
 				false
 			}
-			Actions::Buzz => {
-				let msg =
-					serde_json::to_string(&ClientMessage::Buzz).expect_throw("serializing message");
-				self.ws.send(Message::Text(msg)).expect_throw("sending message");
+			Actions::OpenDisplay => {
+				let id = ctx.props().id;
+				let join = JoinQuery { role: Role::Spectator, resume: None, ..self.join.clone() };
+				let history = ctx.link().history().expect("access to history");
+				if history.push_with_query(Routes::Display { id }, join).is_err() {
+					history.push(Routes::Display { id });
+				}
+				false
+			}
+			Actions::Buzz(pressed_at) => {
+				self.send(ClientMessage::Buzz { pressed_at: Some(pressed_at) });
 				false
 			}
 			Actions::FreeBuzzer => {
-				let msg = serde_json::to_string(&ClientMessage::FreeBuzzer)
-					.expect_throw("serializing message");
-				self.ws.send(Message::Text(msg)).expect_throw("sending message");
+				self.send(ClientMessage::FreeBuzzer);
 				false
 			}
-			Actions::Reconnect => {
-				let on_message = ctx.link().callback(Room::on_message);
-				self.ws = Room::connect_ws(ctx.props().id, &self.my_token, on_message);
+			Actions::NextBuzzer => {
+				self.send(ClientMessage::NextBuzzer);
+				false
+			}
+			Actions::CountdownInput(value) => {
+				self.countdown_input = value;
+				false
+			}
+			Actions::OpenRound => {
+				let countdown_secs = self.countdown_input.trim().parse().ok();
+				self.send(ClientMessage::OpenRound { countdown_secs });
+				false
+			}
+			Actions::LockRound => {
+				self.send(ClientMessage::LockRound);
+				false
+			}
+			Actions::RevealRound => {
+				self.send(ClientMessage::RevealRound);
+				false
+			}
+			Actions::ResetRound => {
+				self.send(ClientMessage::ResetRound);
 				false
 			}
+			Actions::AwardPoints(id, points) => {
+				self.send(ClientMessage::AwardPoints { id, points });
+				false
+			}
+			Actions::JudgeAnswer(correct) => {
+				self.send(ClientMessage::JudgeAnswer { correct });
+				false
+			}
+			Actions::TeamInput(value) => {
+				self.team_input = value;
+				false
+			}
+			Actions::CreateTeam => {
+				let team = std::mem::take(&mut self.team_input);
+				self.send(ClientMessage::CreateTeam(team));
+				true
+			}
+			Actions::RemoveTeam(team) => {
+				self.send(ClientMessage::RemoveTeam(team));
+				false
+			}
+			Actions::JoinTeam(team) => {
+				self.send(ClientMessage::JoinTeam(team));
+				false
+			}
+			Actions::TransferHost(id) => {
+				self.send(ClientMessage::TransferHost(id));
+				false
+			}
+			Actions::Kick(id) => {
+				let message = format!("Reason for kicking {}:", self.name_of(id));
+				if let Some(reason) = Self::ask_reason(&message) {
+					let reason = (!reason.is_empty()).then_some(reason);
+					self.send(ClientMessage::Kick { id, reason });
+				}
+				false
+			}
+			Actions::Ban(id) => {
+				let message = format!("Reason for banning {}:", self.name_of(id));
+				if let Some(reason) = Self::ask_reason(&message) {
+					let reason = (!reason.is_empty()).then_some(reason);
+					self.send(ClientMessage::Ban { id, reason });
+				}
+				false
+			}
+			Actions::Kicked { id, reason } => {
+				if id == self.my_id && !ctx.props().spectator {
+					self.kicked = Some(reason.unwrap_or_else(|| "No reason given.".to_owned()));
+					true
+				} else {
+					false
+				}
+			}
+			Actions::Closed => {
+				self.join_error = Some("The room was closed.".to_owned());
+				true
+			}
+			Actions::Reconnect => {
+				self.connect(ctx);
+				true
+			}
+			Actions::Connected(number) => {
+				if number != self.connection_number {
+					return false;
+				}
+				self.connection = Connection::Connected;
+				self.reconnect_attempts = 0;
+				self.ws_failures = 0;
+				self.flush_outbox();
+				true
+			}
+			Actions::Disconnected(number) => {
+				if number != self.connection_number
+					|| self.kicked.is_some()
+					|| self.join_error.is_some()
+				{
+					return false;
+				}
+				self.ws = None;
+				let ws_failed = self.connection == Connection::Connecting
+					&& self.transport == Transport::WebSocket;
+				if ws_failed {
+					self.ws_failures = self.ws_failures.saturating_add(1);
+				}
+				// Fall back if the very first websocket or several in a row never
+				// connected.
+				if ws_failed && (self.connection_number == 1 || self.ws_failures >= MAX_WS_FAILURES)
+				{
+					log::info!("Websocket could not connect, falling back to server-sent events");
+					self.transport = Transport::EventStream;
+					self.connect(ctx);
+				} else {
+					self.schedule_reconnect(ctx);
+				}
+				true
+			}
+			Actions::Event(event) => {
+				if self.track_sequence(&event) {
+					self.update(ctx, Self::on_event(event.message))
+				} else {
+					false
+				}
+			}
+			Actions::Retry(number) => {
+				if number == self.connection_number && self.ws.is_none() {
+					self.connect(ctx);
+				}
+				true
+			}
 			Actions::RoomState(state) => {
 				self.state = state;
 				true
 			}
-			Actions::Buzzed(buzzed) => {
-				self.state.buzzed = buzzed;
+			Actions::BuzzQueue(queue) => {
+				self.state.buzz_queue = queue;
 				true
 			}
+			Actions::Scores(scores) => {
+				self.state.scores = scores;
+				true
+			}
+			Actions::Teams(teams) => {
+				self.state.teams = teams;
+				true
+			}
+			Actions::Round(round) => {
+				self.state.round = round;
+				true
+			}
+			Actions::Tick(remaining) => {
+				self.state.round.remaining_ms = Some(remaining);
+				true
+			}
+			Actions::Error { code, message } => {
+				match code {
+					// Closes the connection, reconnecting would fail the same way.
+					ErrorCode::IncompatibleProtocol | ErrorCode::MalformedMessage => {
+						self.join_error = Some(message);
+					}
+					_ => {
+						if self.errors.len() >= MAX_ERRORS {
+							self.errors.remove(0);
+						}
+						self.errors.push(message);
+					}
+				}
+				true
+			}
+			Actions::DismissError(index) => {
+				if index < self.errors.len() {
+					self.errors.remove(index);
+				}
+				true
+			}
+			Actions::Welcome(hello) => {
+				log::debug!("Speaking websocket protocol {hello:?}");
+				let supported = websocket::MIN_PROTOCOL_VERSION..=websocket::PROTOCOL_VERSION;
+				if supported.contains(&hello.version) {
+					false
+				} else {
+					self.join_error = Some(
+						"The server speaks an incompatible protocol version, please reload the page."
+							.to_owned(),
+					);
+					true
+				}
+			}
+			Actions::Resume(token) => {
+				self.join.resume = Some(token);
+				false
+			}
+			Actions::Ping(server_time) => {
+				self.send(ClientMessage::Pong { server_time, client_time: js_sys::Date::now() });
+				false
+			}
 		}
 	}
 
+	#[allow(clippy::if_then_some_else_none)] // generated by yew's boolean attributes
 	fn view(&self, ctx: &yew::Context<Self>) -> Html {
 		// TODO: Add keyboard listener by batch_callback.
 
+		if let Some(reason) = self.kicked.as_ref() {
+			return Self::view_error("You were removed from the room.", reason);
+		}
+		if let Some(reason) = self.join_error.as_ref() {
+			return Self::view_error("Could not join the room.", reason);
+		}
+		if ctx.props().spectator {
+			return self.view_display();
+		}
+
 		let leave = ctx.link().callback(|_e| Actions::LeaveRoom);
 		let reconnect = ctx.link().callback(|_e| Actions::Reconnect);
-		let buzz = ctx.link().callback(|_e| Actions::Buzz);
+		let invite = ctx.link().callback(|_e| Actions::Invite);
+		let open_display = ctx.link().callback(|_e| Actions::OpenDisplay);
+		let buzz = ctx.link().callback(|_e| Actions::Buzz(js_sys::Date::now()));
 		let free_buzzer = ctx.link().callback(|_e| Actions::FreeBuzzer);
+		let next_buzzer = ctx.link().callback(|_e| Actions::NextBuzzer);
+		let correct = ctx.link().callback(|_e| Actions::JudgeAnswer(true));
+		let incorrect = ctx.link().callback(|_e| Actions::JudgeAnswer(false));
 
-		let main_stage = if let Some(buzzed) = self.state.buzzed.as_ref() {
+		let locked_out = self.state.round.locked_out.contains_key(&self.my_id);
+		let buzz_button = html! {
+			<button class="button is-danger is-rounded is-large" onclick={buzz} disabled={locked_out}>
+				if locked_out { { "Locked out" } } else { { "Buzz!" } }
+			</button>
+		};
+
+		let main_stage = if let Some((buzzed, waiting)) = self.state.buzz_queue.split_first() {
+			let waiting = waiting
+				.iter()
+				.map(
+					|entry| html! { <li>{ format!("{} (+{:.0} ms)", entry.name, entry.delta_ms) }</li> },
+				)
+				.collect::<Html>();
+			let team = buzzed.team.as_ref().map(|team| format!(" ({team})")).unwrap_or_default();
 			html! {
 				<>
-				{ buzzed }{ " has buzzed!" }
-				if self.my_name == self.state.host {
+				<p>{ &buzzed.name }{ team }{ " has buzzed!" }</p>
+				if self.is_host() {
+					<button class="button is-success" onclick={correct}>{ "Correct" }</button>
+					<button class="button is-danger" onclick={incorrect}>{ "Incorrect" }</button>
+					<button class="button" onclick={next_buzzer}>{ "Next Buzzer" }</button>
 					<button class="button" onclick={free_buzzer}>{ "Free Buzzer" }</button>
+				} else if !self.state.buzz_queue.iter().any(|entry| entry.id == self.my_id) {
+					{ buzz_button }
 				}
+				<ol start="2">{ waiting }</ol>
 				</>
 			}
 		} else {
-			html! {
-				<button class="button is-danger is-rounded is-large" onclick={buzz}>
-					{ "Buzz!" }
-				</button>
-			}
+			buzz_button
 		};
 
-		let members = self
-			.state
-			.members
-			.iter()
-			.map(|name| {
-				html! {
-					<tr><td>
-						if *name == self.my_name {
-							<strong>{ name }</strong>
-						} else {
-							{ name }
-						}
-					</td></tr>
-				}
-			})
-			.collect::<Html>();
-
 		html! {
 			<>
 			<div class="columns is-fullheight">
 				<div class="column is-four-fifths">
 					<div class="section is-fullheight is-large">
 						<div class="container is-fullheight is-vcentered is-centered">
+							{ self.view_round(ctx) }
 							{ main_stage }
 						</div>
 					</div>
 				</div>
 				<div class="column content is-medium">
+					{ self.view_connection() }
+					{ self.view_errors(ctx) }
 					<button class="button" onclick={leave}>{ "Leave" }</button>
-					<button class="button" onclick={reconnect}>{ "Reconnect" }</button>
-					<table>
-						<thead><tr><td>
-							{ "Room Members" }
-						</td></tr></thead>
-						{ members }
-					</table>
+					if self.connection != Connection::Connected {
+						<button class="button" onclick={reconnect}>{ "Reconnect now" }</button>
+					}
+					<button class="button" onclick={open_display}>{ "Display" }</button>
+					if self.is_host() {
+						<button class="button" onclick={invite}>{ "Invite" }</button>
+					}
+					{ self.view_teams_controls(ctx) }
+					{ self.view_members(ctx) }
 				</div>
 			</div>
 			</>
@@ -2,9 +2,14 @@
 
 use api_types::{LoginInfo, LoginResponse};
 use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::UnwrapThrowExt;
 use yew::{html, Callback, Component};
-use yew_router::{history::History, prelude::RouterScopeExt};
+use yew_router::{
+	history::{AnyHistory, History, Location},
+	prelude::RouterScopeExt,
+	Routable,
+};
 
 use super::{get_value_from_input_event, GlobalStateAction, UserData};
 use crate::routes::{GlobalContext, Routes};
@@ -13,13 +18,60 @@ use crate::routes::{GlobalContext, Routes};
 pub enum Actions {
 	/// Start saving user info
 	Save,
-	/// Save the new information to the global state and go back to the index
-	/// page
+	/// Save the new information to the global state and go back to the page
+	/// to return to
 	SaveGoBack(UserData),
+	/// Saving failed with the error
+	SaveFailed(String),
 	/// Input value change of name field
 	InputNameValue(String),
 }
 
+/// Query of the user editor
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserEditQuery {
+	/// Path and query string of the page to return to after saving, the index
+	/// page by default
+	#[serde(default)]
+	pub return_to: Option<String>,
+}
+
+impl UserEditQuery {
+	/// Open the user editor, returning to the current page after saving.
+	pub fn edit_and_return(history: &AnyHistory) {
+		let location = history.location();
+		let return_to = format!("{}{}", location.pathname(), location.search());
+		let query = Self { return_to: Some(return_to) };
+		if history.push_with_query(Routes::UserEdit, query).is_err() {
+			history.push(Routes::UserEdit);
+		}
+	}
+
+	/// Go to the page to return to. Only rooms and their displays are returned
+	/// to, everything else goes to the index page.
+	fn go_back(self, history: &AnyHistory) {
+		let return_to = self.return_to.unwrap_or_default();
+		let (path, query) = return_to.split_once('?').unwrap_or((&return_to, ""));
+		let route = match Routes::recognize(path) {
+			Some(route @ (Routes::Room { .. } | Routes::Display { .. })) => route,
+			_ => Routes::Index,
+		};
+		// Spaces might be encoded as `+`, literal `+` are always escaped.
+		let decode =
+			|value: &str| js_sys::decode_uri_component(&value.replace('+', " ")).ok()?.as_string();
+		let query: Vec<(String, String)> = query
+			.split('&')
+			.filter_map(|pair| {
+				let (key, value) = pair.split_once('=')?;
+				Some((decode(key)?, decode(value)?))
+			})
+			.collect();
+		if query.is_empty() || history.push_with_query(route, query).is_err() {
+			history.push(route);
+		}
+	}
+}
+
 /// User edit component
 #[derive(Debug, Default)]
 pub struct UserEdit {
@@ -27,6 +79,8 @@ pub struct UserEdit {
 	input_name: String,
 	/// Additional CSS class of the input field
 	input_class: Option<&'static str>,
+	/// Error of the last attempt to save
+	error: Option<String>,
 }
 
 impl Component for UserEdit {
@@ -43,24 +97,46 @@ impl Component for UserEdit {
 			Actions::Save => {
 				let login_info = LoginInfo { username: self.input_name.clone() };
 				if login_info.is_valid() {
+					// Keep the user ID by logging in with the current token.
+					let token = ctx
+						.link()
+						.context::<GlobalContext>(Callback::noop())
+						.and_then(|(global_state, _)| global_state.user.clone())
+						.map(|user| user.token);
 					ctx.link()
 						.callback_future_once(|login_info: LoginInfo| async move {
 							// Login with the new name
-							let response = Request::post("/login")
+							let mut request = Request::post("/login");
+							if let Some(token) = token {
+								request =
+									request.header("Authorization", &format!("Bearer {token}"));
+							}
+							let response = request
 								.body(
 									serde_json::to_string(&login_info)
 										.expect_throw("serialize JSON"),
 								)
 								.header("Content-Type", "application/json")
 								.send()
-								.await
-								.expect_throw("login request");
+								.await;
+							let response = match response {
+								Ok(response) if response.ok() => response,
+								Ok(response) => {
+									let error = response
+										.text()
+										.await
+										.unwrap_or_else(|_| response.status_text());
+									return Actions::SaveFailed(error);
+								}
+								Err(err) => return Actions::SaveFailed(err.to_string()),
+							};
 
-							let login_resp: LoginResponse = response
-								.json()
-								.await
-								.expect_throw("deserialize JSON from response");
+							let login_resp: LoginResponse = match response.json().await {
+								Ok(login_resp) => login_resp,
+								Err(err) => return Actions::SaveFailed(err.to_string()),
+							};
 							Actions::SaveGoBack(UserData {
+								id: login_resp.id,
 								name: login_info.username,
 								token: login_resp.token,
 							})
@@ -68,6 +144,7 @@ impl Component for UserEdit {
 						.emit(login_info);
 
 					self.input_class = Some("is-loading");
+					self.error = None;
 					true
 				} else {
 					self.input_class = Some("is-danger");
@@ -82,12 +159,18 @@ impl Component for UserEdit {
 					.0
 					.dispatch(GlobalStateAction::ReplaceUser(user_data));
 
-				// Go back to index page
+				// Go back to the page the user came from
 				let history = ctx.link().history().expect("access to history");
-				history.push(Routes::Index);
+				let query = history.location().query::<UserEditQuery>().unwrap_or_default();
+				query.go_back(&history);
 
 				false
 			}
+			Actions::SaveFailed(error) => {
+				self.input_class = Some("is-danger");
+				self.error = Some(error);
+				true
+			}
 			Actions::InputNameValue(value) => {
 				self.input_name = value;
 				let login_info = LoginInfo { username: self.input_name.clone() };
@@ -120,6 +203,9 @@ impl Component for UserEdit {
 						<input class={classes} type="text" placeholder="<name>" oninput={input_change} />
 					</div>
 				</div>
+				if let Some(error) = self.error.as_ref() {
+					<div class="notification is-danger is-light">{ error }</div>
+				}
 				<div class="field">
 					<div class="control">
 						<input type="submit" class="button is-fullwidth" value="Save" />
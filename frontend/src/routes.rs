@@ -21,6 +21,12 @@ pub enum Routes {
 		/// Room ID
 		id: Uuid,
 	},
+	/// Large-format display of a room for spectators
+	#[at("/:id/display")]
+	Display {
+		/// Room ID
+		id: Uuid,
+	},
 	/// Not Found Page
 	#[not_found]
 	#[at("/404")]
@@ -32,7 +38,11 @@ fn switch(routes: &Routes) -> Html {
 	match routes {
 		Routes::Index => html! { <Index /> },
 		Routes::UserEdit => html! { <UserEdit /> },
-		Routes::Room { id } => html! { <Room id={*id} /> },
+		// Distinct keys connect anew when switching between playing and display.
+		Routes::Room { id } => html! { <Room key={format!("{id}")} id={*id} /> },
+		Routes::Display { id } => {
+			html! { <Room key={format!("{id}/display")} id={*id} spectator=true /> }
+		}
 		Routes::NotFound => html! { <h1>{ "404 - Not Found!" }</h1> },
 	}
 }
@@ -0,0 +1,4 @@
+CREATE TABLE rooms (
+	id TEXT PRIMARY KEY NOT NULL,
+	data TEXT NOT NULL
+);
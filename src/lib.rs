@@ -1,12 +1,17 @@
 //! Crate library
 
+mod password;
 mod rooms;
 mod server;
 mod settings;
+mod storage;
+mod tls;
+
+use std::net::SocketAddr;
 
-use axum::Router;
 use color_eyre::Result;
 use settings::Settings;
+use tls::TlsListener;
 use tower_http::trace::{DefaultMakeSpan, TraceLayer};
 use tracing_subscriber::EnvFilter;
 
@@ -22,20 +27,57 @@ pub async fn run() -> Result<()> {
 		.add_directive("want=info".parse()?)
 		.add_directive("tungstenite=info".parse()?)
 		.add_directive("tokio=info".parse()?)
+		.add_directive("rustls=info".parse()?)
 		.add_directive("sqlx=error".parse()?);
 	tracing_subscriber::fmt().with_env_filter(filter).init();
 
 	// Start server
 	tracing::info!("Starting server and listening on {}", settings.bind);
-	axum::Server::bind(&settings.bind).serve(server_app(settings).into_make_service()).await?;
+	let bind = settings.bind;
+	let tls = settings.tls.clone();
+	let server::Routes { app, metrics, shutdown } = server_app(settings).await?;
+	if let Some((metrics_bind, metrics)) = metrics {
+		tracing::info!("Serving metrics on {metrics_bind}");
+		tokio::spawn(async move {
+			let server = axum::Server::bind(&metrics_bind).serve(metrics.into_make_service());
+			if let Err(err) = server.await {
+				tracing::error!("Metrics server failed: {err}");
+			}
+		});
+	}
+	let app = app.into_make_service_with_connect_info::<SocketAddr>();
+	match tls {
+		Some(tls) => {
+			let listener = TlsListener::bind(bind, &tls).await?;
+			if let Some(redirect_bind) = tls.redirect_bind {
+				tracing::info!("Redirecting HTTP on {redirect_bind} to HTTPS");
+				let redirect = tls::redirect_app(bind.port());
+				tokio::spawn(async move {
+					let server =
+						axum::Server::bind(&redirect_bind).serve(redirect.into_make_service());
+					if let Err(err) = server.await {
+						tracing::error!("HTTP redirect server failed: {err}");
+					}
+				});
+			}
+			axum::Server::builder(listener)
+				.serve(app)
+				.with_graceful_shutdown(shutdown.wait())
+				.await?;
+		}
+		None => {
+			axum::Server::bind(&bind).serve(app).with_graceful_shutdown(shutdown.wait()).await?;
+		}
+	}
 
 	Ok(())
 }
 
-/// Webserver routes
-#[must_use]
-pub fn server_app(settings: Settings) -> Router {
-	server::routes(settings).layer(
+/// Webserver routes, with tracing of the app's requests
+pub async fn server_app(settings: Settings) -> Result<server::Routes> {
+	let mut routes = server::routes(settings).await?;
+	routes.app = routes.app.layer(
 		TraceLayer::new_for_http().make_span_with(DefaultMakeSpan::default().include_headers(true)),
-	)
+	);
+	Ok(routes)
 }
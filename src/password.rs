@@ -0,0 +1,143 @@
+//! Hashing of room passwords
+
+use std::{fmt, num::NonZeroU32, str::FromStr};
+
+use ring::pbkdf2;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Key derivation of the hashes
+static ALGORITHM: pbkdf2::Algorithm = pbkdf2::PBKDF2_HMAC_SHA256;
+/// Identifier of the key derivation in encoded hashes
+const ALGORITHM_ID: &str = "pbkdf2-sha256";
+/// Iterations of the key derivation for new hashes
+const ITERATIONS: u32 = 100_000;
+/// Length of the hashes in bytes
+const HASH_LEN: usize = 32;
+
+/// Invalid encoded password hash
+#[derive(Debug, Error)]
+#[error("Invalid password hash")]
+pub struct InvalidHash;
+
+/// Salted PBKDF2 hash of a password. Encoded as
+/// `pbkdf2-sha256$<iterations>$<salt>$<hash>` with base64 salt and hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct PasswordHash {
+	/// Iterations of the key derivation
+	iterations: NonZeroU32,
+	/// Random salt
+	salt: Vec<u8>,
+	/// Derived key
+	hash: Vec<u8>,
+}
+
+impl PasswordHash {
+	/// Hash the password with a new random salt.
+	#[must_use]
+	pub fn new(password: &str) -> Self {
+		let iterations = NonZeroU32::new(ITERATIONS).unwrap_or(NonZeroU32::MIN);
+		// Version 4 UUIDs are random.
+		let salt = Uuid::new_v4().into_bytes().to_vec();
+		let mut hash = vec![0; HASH_LEN];
+		pbkdf2::derive(ALGORITHM, iterations, &salt, password.as_bytes(), &mut hash);
+		Self { iterations, salt, hash }
+	}
+
+	/// Check whether the password matches the hash, in constant time.
+	#[must_use]
+	pub fn verify(&self, password: &str) -> bool {
+		pbkdf2::verify(ALGORITHM, self.iterations, &self.salt, password.as_bytes(), &self.hash)
+			.is_ok()
+	}
+}
+
+impl fmt::Display for PasswordHash {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let salt = base64::encode(&self.salt);
+		let hash = base64::encode(&self.hash);
+		write!(f, "{ALGORITHM_ID}${}${salt}${hash}", self.iterations)
+	}
+}
+
+impl FromStr for PasswordHash {
+	type Err = InvalidHash;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut parts = s.split('$');
+		if parts.next() != Some(ALGORITHM_ID) {
+			return Err(InvalidHash);
+		}
+		let iterations = parts.next().and_then(|it| it.parse().ok()).ok_or(InvalidHash)?;
+		let salt = parts.next().and_then(|salt| base64::decode(salt).ok()).ok_or(InvalidHash)?;
+		let hash = parts.next().and_then(|hash| base64::decode(hash).ok()).ok_or(InvalidHash)?;
+		if parts.next().is_some() || hash.is_empty() {
+			return Err(InvalidHash);
+		}
+		Ok(Self { iterations, salt, hash })
+	}
+}
+
+impl From<PasswordHash> for String {
+	fn from(hash: PasswordHash) -> Self {
+		hash.to_string()
+	}
+}
+
+impl TryFrom<String> for PasswordHash {
+	type Error = InvalidHash;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{InvalidHash, PasswordHash};
+
+	/// Only the hashed password verifies.
+	#[test]
+	fn verify() {
+		let hash = PasswordHash::new("correct horse");
+		assert!(hash.verify("correct horse"));
+		assert!(!hash.verify("correct horse "));
+		assert!(!hash.verify(""));
+	}
+
+	/// Hashes of the same password differ by their salt.
+	#[test]
+	fn salted() {
+		let first = PasswordHash::new("secret");
+		let second = PasswordHash::new("secret");
+		assert_ne!(first.salt, second.salt);
+		assert_ne!(first.hash, second.hash);
+		assert!(second.verify("secret"));
+	}
+
+	/// Encoded hashes decode to the same hash.
+	#[test]
+	fn encoding_round_trip() -> Result<(), InvalidHash> {
+		let hash = PasswordHash::new("secret");
+		let encoded = hash.to_string();
+		assert!(encoded.starts_with("pbkdf2-sha256$100000$"));
+
+		let decoded: PasswordHash = encoded.parse()?;
+		assert_eq!(decoded, hash);
+		assert!(decoded.verify("secret"));
+		Ok(())
+	}
+
+	/// Strings that are not encoded hashes are rejected.
+	#[test]
+	fn invalid_encoding() {
+		let hash = PasswordHash::new("secret").to_string();
+		assert!("secret".parse::<PasswordHash>().is_err());
+		assert!("pbkdf2-sha256$0$AAAA$AAAA".parse::<PasswordHash>().is_err());
+		assert!("pbkdf2-sha256$1$!$AAAA".parse::<PasswordHash>().is_err());
+		assert!("pbkdf2-sha256$1$AAAA$".parse::<PasswordHash>().is_err());
+		assert!(format!("{hash}$AAAA").parse::<PasswordHash>().is_err());
+	}
+}
@@ -1,63 +1,510 @@
 //! Buzzer rooms
 
-use std::collections::HashMap;
+use std::{
+	collections::{BTreeMap, HashMap, HashSet},
+	future,
+	sync::{
+		atomic::{AtomicBool, AtomicUsize, Ordering},
+		Arc, Mutex, PoisonError,
+	},
+	time::Duration,
+};
 
 use api_types::{
-	websocket::{self, ServerMessage},
-	LoginInfo,
+	websocket::{self, BuzzEntry, Round, RoundPhase, Score, ServerEvent, ServerMessage, Team},
+	Lockout, LoginInfo, RoomAccessInfo,
 };
 use serde::{Deserialize, Serialize};
-use tokio::sync::{broadcast, RwLock};
+use time::OffsetDateTime;
+use tokio::{
+	sync::{broadcast, broadcast::error::RecvError, RwLock, RwLockWriteGuard},
+	task::{self, JoinError},
+};
+use uuid::Uuid;
+
+use crate::{password::PasswordHash, settings::RoomSettings};
+
+/// Number of room events buffered for slow connections. Connections lagging
+/// further behind get the full state again.
+const EVENT_BUFFER: usize = 128;
+/// Maximum number of resumable sessions of a member. The least recently used
+/// session is dropped for a new one.
+const MAX_SESSIONS: usize = 8;
+
+/// Current time in milliseconds since the UNIX epoch.
+#[must_use]
+pub fn now_millis() -> f64 {
+	OffsetDateTime::now_utc().unix_timestamp_nanos() as f64 / 1_000_000.0
+}
+
+/// Normalize a display name for comparing names case-insensitively.
+fn normalize_name(name: &str) -> String {
+	name.trim().to_lowercase()
+}
 
 /// User data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserData {
-	/// Username
+	/// Stable user ID
+	pub id: Uuid,
+	/// Display name
 	pub name: String,
 }
 
 impl From<LoginInfo> for UserData {
 	fn from(login: LoginInfo) -> Self {
-		Self { name: login.username }
+		Self { id: Uuid::new_v4(), name: login.username.trim().to_owned() }
+	}
+}
+
+/// Member of a room
+#[derive(Debug, Clone)]
+pub struct Member {
+	/// User data
+	pub user: UserData,
+	/// Team the member belongs to
+	pub team: Option<String>,
+	/// Number of open connections of the member, e.g. from multiple devices
+	pub connections: usize,
+	/// Time when the last connection closed in milliseconds since the UNIX
+	/// epoch, None while connected. Disconnected members keep their seat for
+	/// the reconnection grace period.
+	pub disconnected_at: Option<f64>,
+	/// Resumable sessions of the member's connections by resume token
+	pub sessions: HashMap<Uuid, Session>,
+}
+
+/// Resumable session of a member's connection
+#[derive(Debug, Clone, Copy)]
+pub struct Session {
+	/// Last latency measurement of the connection
+	pub latency: Option<Latency>,
+	/// Time the session was last started or resumed in milliseconds since the
+	/// UNIX epoch
+	pub used_at: f64,
+}
+
+/// Persistent part of a member
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberSnapshot {
+	/// User data
+	pub user: UserData,
+	/// Team the member belongs to
+	pub team: Option<String>,
+}
+
+/// Latency measurement of a connection
+#[derive(Debug, Clone, Copy)]
+pub struct Latency {
+	/// Round-trip time in milliseconds
+	pub rtt: f64,
+	/// Offset of the client's clock to the server's clock in milliseconds
+	pub clock_offset: f64,
+}
+
+impl Latency {
+	/// Compute the latency from a ping's server time and the client's time of
+	/// receiving the ping, assuming symmetric network delays.
+	#[must_use]
+	pub fn measure(server_time: f64, client_time: f64) -> Self {
+		let rtt = (now_millis() - server_time).max(0.0);
+		let clock_offset = client_time - (server_time + rtt / 2.0);
+		Self { rtt, clock_offset }
+	}
+
+	/// Convert a client timestamp to server time.
+	#[must_use]
+	pub fn to_server_time(self, client_time: f64) -> f64 {
+		client_time - self.clock_offset
+	}
+}
+
+/// Entry of the buzz queue
+#[derive(Debug, Clone)]
+struct Buzz {
+	/// User ID of the person who buzzed
+	id: Uuid,
+	/// Name of the person who buzzed
+	name: String,
+	/// Team of the person who buzzed
+	team: Option<String>,
+	/// Estimated time of pressing the buzzer in milliseconds since the UNIX
+	/// epoch
+	time: f64,
+	/// Round-trip time of the person's connection in milliseconds, if known
+	rtt: Option<f64>,
+}
+
+impl Buzz {
+	/// Convert the buzz queue to the API representation, with time deltas
+	/// relative to the first buzz.
+	fn to_entries(queue: &[Self]) -> Vec<BuzzEntry> {
+		let first = queue.first().map_or(0.0, |buzz| buzz.time);
+		queue
+			.iter()
+			.map(|buzz| BuzzEntry {
+				id: buzz.id,
+				name: buzz.name.clone(),
+				team: buzz.team.clone(),
+				delta_ms: buzz.time - first,
+				rtt_ms: buzz.rtt,
+			})
+			.collect()
 	}
 }
 
+/// State of the current question round
+#[derive(Debug, Clone, Default)]
+struct RoundState {
+	/// Current phase of the round
+	phase: RoundPhase,
+	/// Time of arming the buzzer in milliseconds since the UNIX epoch
+	opened_at: f64,
+	/// Time when the buzzer locks in milliseconds since the UNIX epoch, if the
+	/// round has a countdown
+	deadline: Option<f64>,
+	/// User IDs of the persons who buzzed before the buzzer was armed
+	false_starts: Vec<Uuid>,
+	/// User IDs of the persons who buzzed after the buzzer locked. Their false
+	/// starts and lockouts carry over to the next question.
+	carry_over: HashSet<Uuid>,
+	/// Persons who are locked out from buzzing by user ID, with the end of the
+	/// lockout in milliseconds since the UNIX epoch. None means they are
+	/// locked out for the rest of the question.
+	lockouts: HashMap<Uuid, Option<f64>>,
+	/// Teams that buzzed since the buzzer was armed or freed. Each team gets a
+	/// single attempt, even after its buzz was passed on or judged wrong.
+	teams_buzzed: HashSet<String>,
+	/// Time when the fairness window of the first buzz ends in milliseconds
+	/// since the UNIX epoch. Until then, later buzzes might still take the
+	/// first place, so the buzz queue is not announced yet.
+	announce_at: Option<f64>,
+}
+
+impl RoundState {
+	/// Lock the person out from buzzing. An existing longer lockout is kept.
+	fn lock_out(&mut self, id: Uuid, lockout: Lockout) {
+		let until = match lockout {
+			Lockout::Off => return,
+			Lockout::Millis(millis) => Some(now_millis() + millis as f64),
+			Lockout::RestOfQuestion => None,
+		};
+		let entry = self.lockouts.entry(id).or_insert(until);
+		*entry = entry.zip(until).map(|(current, until)| current.max(until));
+	}
+
+	/// Convert the round to the API representation.
+	fn to_round(&self) -> Round {
+		Round {
+			phase: self.phase,
+			deadline: self.deadline,
+			remaining_ms: self.deadline.map(|deadline| (deadline - now_millis()).max(0.0)),
+			false_starts: self.false_starts.clone(),
+			locked_out: self
+				.lockouts
+				.iter()
+				.map(|(&id, until)| (id, until.map(|until| (until - now_millis()).max(0.0))))
+				.collect(),
+		}
+	}
+}
+
+/// Entry of the score history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreEntry {
+	/// Time of awarding the points in milliseconds since the UNIX epoch
+	pub time: f64,
+	/// User ID of the person who got the points
+	pub id: Uuid,
+	/// Name of the person who got the points
+	pub name: String,
+	/// Team of the person at that time
+	pub team: Option<String>,
+	/// Awarded points, negative for deducted points
+	pub points: i64,
+}
+
+/// Access restrictions of a room
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoomAccess {
+	/// Hash of the password required to join the room
+	pub password_hash: Option<PasswordHash>,
+	/// Whether the room can only be joined with an invite token
+	pub invite_only: bool,
+	/// Whether the room is listed publicly
+	pub public: bool,
+	/// User ID of the person who created the room, who can close it
+	pub creator: Option<Uuid>,
+}
+
+impl RoomAccess {
+	/// Check whether joining is allowed with the given password. A valid invite
+	/// always allows joining.
+	#[must_use]
+	pub fn allows(&self, password: Option<&str>, invited: bool) -> bool {
+		if invited {
+			return true;
+		}
+		!self.invite_only
+			&& self
+				.password_hash
+				.as_ref()
+				.is_none_or(|hash| password.is_some_and(|pw| hash.verify(pw)))
+	}
+
+	/// Check whether joining is allowed like [`Self::allows`]. Verifying the
+	/// password is slow on purpose, so it runs on the blocking thread pool.
+	pub async fn allows_blocking(
+		&self,
+		password: Option<String>,
+		invited: bool,
+	) -> Result<bool, JoinError> {
+		if invited || self.password_hash.is_none() {
+			return Ok(self.allows(None, invited));
+		}
+		let access = self.clone();
+		task::spawn_blocking(move || access.allows(password.as_deref(), invited)).await
+	}
+
+	/// Get the access requirements for clients, including whether the given
+	/// password or invite grants access.
+	pub async fn info(
+		&self,
+		password: Option<String>,
+		invited: bool,
+	) -> Result<RoomAccessInfo, JoinError> {
+		Ok(RoomAccessInfo {
+			password: self.password_hash.is_some(),
+			invite_only: self.invite_only,
+			granted: self.allows_blocking(password, invited).await?,
+		})
+	}
+}
+
+/// Persistent part of a room's state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomSnapshot {
+	/// Room configuration
+	pub settings: RoomSettings,
+	/// Access restrictions of the room
+	pub access: RoomAccess,
+	/// Points of every user who scored in this room
+	pub scores: HashMap<Uuid, Score>,
+	/// Teams of the room and their points
+	pub teams: BTreeMap<String, i64>,
+	/// History of awarded points
+	pub history: Vec<ScoreEntry>,
+	/// User IDs of the persons who are banned from the room
+	pub banned: HashSet<Uuid>,
+	/// Names of the persons who are banned from the room, see
+	/// [`RoomState::is_banned`]
+	pub banned_names: HashSet<String>,
+	/// Members of the room with their teams, restored as disconnected members
+	pub members: Vec<MemberSnapshot>,
+	/// User ID of the host of the room
+	pub host: Option<Uuid>,
+	/// Time of the last member joining or leaving in milliseconds since the
+	/// UNIX epoch
+	pub last_active: f64,
+}
+
 /// Buzzer room state. Lock in the order that is written here to avoid
 /// deadlocks.
 #[derive(Debug)]
 pub struct RoomState {
-	/// Members currently active in the room.
-	members: RwLock<HashMap<String, UserData>>,
-	/// Current host of the room/session,
-	host: RwLock<String>,
-	/// The user who buzzed
-	buzzed: RwLock<Option<String>>,
+	/// Members currently active in the room by user ID.
+	members: RwLock<HashMap<Uuid, Member>>,
+	/// User ID of the current host of the room/session,
+	host: RwLock<Option<Uuid>>,
+	/// Current question round
+	round: RwLock<RoundState>,
+	/// The users who buzzed, in order of buzzing
+	buzz_queue: RwLock<Vec<Buzz>>,
+	/// Points of every user who scored in this room
+	scores: RwLock<HashMap<Uuid, Score>>,
+	/// Teams of the room and their points
+	teams: RwLock<BTreeMap<String, i64>>,
+	/// History of awarded points
+	history: RwLock<Vec<ScoreEntry>>,
+	/// User IDs of the persons who are banned from the room
+	banned: RwLock<HashSet<Uuid>>,
+	/// Names of the persons who are banned from the room, compared
+	/// case-insensitively
+	banned_names: RwLock<HashSet<String>>,
+	/// Time of the last member joining or leaving in milliseconds since the
+	/// UNIX epoch
+	last_active: RwLock<f64>,
 	/// Event sender (publisher)
-	event_pub: broadcast::Sender<ServerMessage>,
+	event_pub: broadcast::Sender<ServerEvent>,
+	/// Sequence number of the last published event, locked while publishing
+	/// to keep the events in order
+	seq: Mutex<u64>,
+	/// Room configuration
+	settings: RoomSettings,
+	/// Access restrictions of the room
+	access: RoomAccess,
+	/// Whether the room was closed
+	closed: AtomicBool,
+	/// Number of connected spectators
+	spectators: AtomicUsize,
 }
 
-impl Default for RoomState {
-	fn default() -> Self {
-		let (sender, _receiver) = broadcast::channel(5);
+impl RoomState {
+	/// Create a new empty room with the given configuration and access
+	/// restrictions.
+	#[must_use]
+	pub fn new(settings: RoomSettings, access: RoomAccess) -> Self {
+		Self::from_snapshot(RoomSnapshot {
+			settings,
+			access,
+			scores: HashMap::new(),
+			teams: BTreeMap::new(),
+			history: Vec::new(),
+			banned: HashSet::new(),
+			banned_names: HashSet::new(),
+			members: Vec::new(),
+			host: None,
+			last_active: now_millis(),
+		})
+	}
+
+	/// Restore a room from its persisted snapshot. The members are restored as
+	/// disconnected, keeping their seat, team and host role if they reconnect.
+	#[must_use]
+	pub fn from_snapshot(snapshot: RoomSnapshot) -> Self {
+		let (sender, _receiver) = broadcast::channel(EVENT_BUFFER);
+		let restored_at = now_millis();
+		let members: HashMap<Uuid, Member> = snapshot
+			.members
+			.into_iter()
+			.map(|member| {
+				let team = member.team.filter(|team| snapshot.teams.contains_key(team));
+				let member = Member {
+					user: member.user,
+					team,
+					connections: 0,
+					disconnected_at: Some(restored_at),
+					sessions: HashMap::new(),
+				};
+				(member.user.id, member)
+			})
+			.collect();
+		let host = snapshot.host.filter(|host| members.contains_key(host));
 		Self {
-			members: RwLock::default(),
-			host: RwLock::default(),
-			buzzed: RwLock::default(),
+			members: RwLock::new(members),
+			host: RwLock::new(host),
+			round: RwLock::default(),
+			buzz_queue: RwLock::default(),
+			scores: RwLock::new(snapshot.scores),
+			teams: RwLock::new(snapshot.teams),
+			history: RwLock::new(snapshot.history),
+			banned: RwLock::new(snapshot.banned),
+			banned_names: RwLock::new(snapshot.banned_names),
+			last_active: RwLock::new(snapshot.last_active),
 			event_pub: sender,
+			seq: Mutex::new(0),
+			settings: snapshot.settings,
+			access: snapshot.access,
+			closed: AtomicBool::new(false),
+			spectators: AtomicUsize::new(0),
 		}
 	}
-}
 
-impl RoomState {
+	/// Take a snapshot of the persistent part of the room.
+	pub async fn snapshot(&self) -> RoomSnapshot {
+		let members = self
+			.members
+			.read()
+			.await
+			.values()
+			.map(|member| MemberSnapshot { user: member.user.clone(), team: member.team.clone() })
+			.collect();
+		RoomSnapshot {
+			settings: self.settings.clone(),
+			access: self.access.clone(),
+			scores: self.scores.read().await.clone(),
+			teams: self.teams.read().await.clone(),
+			history: self.history.read().await.clone(),
+			banned: self.banned.read().await.clone(),
+			banned_names: self.banned_names.read().await.clone(),
+			members,
+			host: self.host().await,
+			last_active: self.last_active().await,
+		}
+	}
+
+	/// Get the time of the last member joining or leaving in milliseconds since
+	/// the UNIX epoch.
+	#[inline]
+	pub async fn last_active(&self) -> f64 {
+		*self.last_active.read().await
+	}
+
+	/// Get the access restrictions of the room.
+	#[inline]
+	#[must_use]
+	pub fn access(&self) -> &RoomAccess {
+		&self.access
+	}
+
+	/// Close the room, disconnecting all members. Members can not join
+	/// afterwards.
+	pub fn close(&self) -> &Self {
+		self.closed.store(true, Ordering::SeqCst);
+		self.publish(ServerMessage::Closed);
+		self
+	}
+
+	/// Notify all connections that the server is restarting. They are closed
+	/// afterwards, but the room is kept.
+	pub fn restart(&self) {
+		self.publish(ServerMessage::Restarting);
+	}
+
+	/// Get whether the room was closed.
+	#[inline]
+	#[must_use]
+	pub fn is_closed(&self) -> bool {
+		self.closed.load(Ordering::SeqCst)
+	}
+
 	/// Subscribe to the event stream of this room.
 	#[inline]
-	pub fn subscribe(&self) -> broadcast::Receiver<ServerMessage> {
+	pub fn subscribe(&self) -> broadcast::Receiver<ServerEvent> {
 		self.event_pub.subscribe()
 	}
 
+	/// Publish an event to all subscribers with the next sequence number.
+	fn publish(&self, message: ServerMessage) {
+		let mut seq = self.seq.lock().unwrap_or_else(PoisonError::into_inner);
+		*seq += 1;
+		self.event_pub.send(ServerEvent { seq: *seq, message }).ok();
+	}
+
+	/// Publish a message outside of the room's event sequence, with the
+	/// sequence number of the last event. Used for messages that are not part
+	/// of the room's state, so missing them is no reason to resync.
+	fn publish_unsequenced(&self, message: ServerMessage) {
+		let seq = self.seq.lock().unwrap_or_else(PoisonError::into_inner);
+		self.event_pub.send(ServerEvent { seq: *seq, message }).ok();
+	}
+
+	/// Get the sequence number of the last published event.
+	#[must_use]
+	pub fn seq(&self) -> u64 {
+		*self.seq.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// Get the full state of the room as event with the sequence number of the
+	/// last event it includes. Subscribe before to not miss any later events.
+	pub async fn state_event(&self) -> ServerEvent {
+		let seq = self.seq();
+		ServerEvent { seq, message: ServerMessage::State(self.state().await) }
+	}
+
 	/// Get the members
 	#[inline]
-	pub async fn members(&self) -> HashMap<String, UserData> {
+	pub async fn members(&self) -> HashMap<Uuid, Member> {
 		self.members.read().await.clone()
 	}
 
@@ -67,89 +514,1475 @@ impl RoomState {
 		self.members.read().await.len()
 	}
 
-	/// Get whether the room is currently empty
+	/// Get the team of the specified member.
+	#[inline]
+	pub async fn team_of(&self, id: Uuid) -> Option<String> {
+		self.members.read().await.get(&id)?.team.clone()
+	}
+
+	/// Get the display name of the specified member, or the last known name
+	/// if they scored in this room before.
+	pub async fn name_of(&self, id: Uuid) -> Option<String> {
+		if let Some(member) = self.members.read().await.get(&id) {
+			return Some(member.user.name.clone());
+		}
+		self.scores.read().await.get(&id).map(|score| score.name.clone())
+	}
+
+	/// Get the number of spectators currently connected to the room.
+	#[inline]
+	#[must_use]
+	pub fn num_spectators(&self) -> usize {
+		self.spectators.load(Ordering::SeqCst)
+	}
+
+	/// Get whether the room is currently empty, i.e. has no members and no
+	/// spectators.
 	#[inline]
 	pub async fn is_empty(&self) -> bool {
-		self.num_members().await == 0
+		self.num_members().await == 0 && self.num_spectators() == 0
 	}
 
-	/// Get the current host of the room.
+	/// Get the user ID of the current host of the room. None means the room
+	/// is empty.
 	#[inline]
-	pub async fn host(&self) -> String {
-		self.host.read().await.clone()
+	pub async fn host(&self) -> Option<Uuid> {
+		*self.host.read().await
+	}
+
+	/// Get the display name of the current host of the room, empty if the room
+	/// is empty.
+	pub async fn host_name(&self) -> String {
+		let members = self.members.read().await;
+		self.host
+			.read()
+			.await
+			.and_then(|host| members.get(&host))
+			.map(|member| member.user.name.clone())
+			.unwrap_or_default()
 	}
 
 	/// Set the host to the specified person.
 	#[inline]
-	async fn set_host(&self, name: &str) -> &Self {
-		*self.host.write().await = name.to_owned();
+	async fn set_host(&self, id: Option<Uuid>) -> &Self {
+		*self.host.write().await = id;
 		self
 	}
 
-	/// Get the current buzzing person of the room.
+	/// Hand the host role to another member. None means the member does not
+	/// exist.
+	pub async fn transfer_host(&self, id: Uuid) -> Option<&Self> {
+		let members = self.members.read().await;
+		if !members.contains_key(&id) {
+			return None;
+		}
+		self.set_host(Some(id)).await;
+		drop(members);
+
+		self.publish(ServerMessage::State(self.state().await));
+		Some(self)
+	}
+
+	/// Kick a member from the room. All their connections receive the
+	/// [`ServerMessage::Kicked`] event and close. None means the member was not
+	/// in the room.
+	pub async fn kick(&self, id: Uuid, reason: Option<String>) -> Option<&Self> {
+		self.remove_member(self.members.write().await, id).await?;
+		self.publish(ServerMessage::Kicked { id, reason });
+		Some(self)
+	}
+
+	/// Ban a person from (re-)joining the room by user ID and name, and kick
+	/// them if present.
+	pub async fn ban(&self, id: Uuid, reason: Option<String>) -> &Self {
+		let name = self.name_of(id).await;
+		self.banned.write().await.insert(id);
+		if let Some(name) = name {
+			self.banned_names.write().await.insert(normalize_name(&name));
+		}
+		self.kick(id, reason).await;
+		self
+	}
+
+	/// Get whether the person is banned from the room, by user ID or name.
+	/// User IDs are cheap to regenerate by logging in again, so the name is
+	/// checked as well. Banned persons can still join with a new login and
+	/// another name.
+	pub async fn is_banned(&self, user: &UserData) -> bool {
+		self.banned.read().await.contains(&user.id)
+			|| self.banned_names.read().await.contains(&normalize_name(&user.name))
+	}
+
+	/// Get everyone who buzzed, in order of buzzing. Empty until the first
+	/// buzz is announced.
+	pub async fn buzz_queue(&self) -> Vec<BuzzEntry> {
+		let round = self.round.read().await;
+		if round.announce_at.is_some() {
+			return Vec::new();
+		}
+		Buzz::to_entries(&self.buzz_queue.read().await)
+	}
+
+	/// Add the specified person to the buzz queue and return their position in
+	/// the queue. None means the person or somebody of their team already
+	/// buzzed, or the buzzer is not armed. Buzzing before the buzzer is armed
+	/// is recorded as a false start.
+	///
+	/// The queue is ordered by the estimated press time (server time in
+	/// milliseconds since the UNIX epoch), which is limited to the fairness
+	/// window before the arrival of the buzz and to the time of arming. Without
+	/// an estimate, the arrival time is used. Presses after the round's
+	/// deadline are rejected. The first buzz is announced once the fairness
+	/// window after its arrival has passed, as later buzzes might have been
+	/// pressed earlier.
+	pub async fn buzz(
+		&self,
+		user: &UserData,
+		pressed_at: Option<f64>,
+		rtt: Option<f64>,
+	) -> Option<usize> {
+		let received = now_millis();
+		let window = self.settings.fairness_window_ms as f64;
+		let time = pressed_at
+			.filter(|time| time.is_finite())
+			.map_or(received, |time| time.clamp(received - window, received));
+
+		let team = self.team_of(user.id).await;
+
+		let mut round = self.round.write().await;
+		match round.phase {
+			RoundPhase::Open => {}
+			phase => {
+				if phase != RoundPhase::Idle {
+					// The false start belongs to the next question, replacing
+					// lockouts of this one.
+					round.lockouts.remove(&user.id);
+					round.carry_over.insert(user.id);
+				}
+				if !round.false_starts.contains(&user.id) {
+					round.false_starts.push(user.id);
+				}
+				round.lock_out(user.id, self.settings.false_start_lockout);
+				self.publish(ServerMessage::Round(round.to_round()));
+				return None;
+			}
+		}
+		let time = time.max(round.opened_at);
+		if round.deadline.is_some_and(|deadline| time > deadline) {
+			return None;
+		}
+
+		if team.as_ref().is_some_and(|team| round.teams_buzzed.contains(team)) {
+			return None;
+		}
+		let mut queue = self.buzz_queue.write().await;
+		if queue.iter().any(|buzz| buzz.id == user.id) {
+			return None;
+		}
+		round.teams_buzzed.extend(team.clone());
+
+		let position = queue.iter().position(|buzz| buzz.time > time).unwrap_or(queue.len());
+		queue.insert(position, Buzz { id: user.id, name: user.name.clone(), team, time, rtt });
+		if queue.len() == 1 && window > 0.0 {
+			round.announce_at = Some(received + window);
+		}
+		if round.announce_at.is_some() {
+			// Announced with the first buzz by the round timer.
+			return Some(position);
+		}
+		let queue = Buzz::to_entries(&queue);
+
+		if position == 0 {
+			self.publish(ServerMessage::Buzzed(queue.first().cloned()));
+		}
+		self.publish(ServerMessage::BuzzQueue(queue));
+
+		Some(position)
+	}
+
+	/// Reject the current buzzing person and pass on to the next one in the
+	/// queue. Returns the new current buzzing person, if anybody is left in the
+	/// queue. None means nobody buzzed.
+	pub async fn next_buzzer(&self) -> Option<Option<Uuid>> {
+		let mut round = self.round.write().await;
+		let mut queue = self.buzz_queue.write().await;
+		round.announce_at = None;
+		drop(round);
+		if queue.is_empty() {
+			return None;
+		}
+
+		queue.remove(0);
+		let buzzed = queue.first().map(|buzz| buzz.id);
+		let queue = Buzz::to_entries(&queue);
+
+		self.publish(ServerMessage::Buzzed(queue.first().cloned()));
+		self.publish(ServerMessage::BuzzQueue(queue));
+
+		Some(buzzed)
+	}
+
+	/// Free the buzzer, i.e. clear the buzz queue.
+	pub async fn free_buzzer(&self) -> &Self {
+		let mut round = self.round.write().await;
+		self.buzz_queue.write().await.clear();
+		round.teams_buzzed.clear();
+		round.announce_at = None;
+		drop(round);
+		self.publish(ServerMessage::Buzzed(None));
+		self.publish(ServerMessage::BuzzQueue(Vec::new()));
+		self
+	}
+
+	/// Announce the first buzz and the buzz queue if the fairness window of the
+	/// first buzz has passed.
+	async fn announce_buzz(&self) -> &Self {
+		let mut round = self.round.write().await;
+		if round.announce_at.is_some_and(|at| at <= now_millis()) {
+			round.announce_at = None;
+			let queue = Buzz::to_entries(&self.buzz_queue.read().await);
+			self.publish(ServerMessage::Buzzed(queue.first().cloned()));
+			self.publish(ServerMessage::BuzzQueue(queue));
+		}
+		self
+	}
+
+	/// Get the time when the first buzz is announced in milliseconds since the
+	/// UNIX epoch. None means there is no pending announcement.
 	#[inline]
-	pub async fn buzzed(&self) -> Option<String> {
-		self.buzzed.read().await.clone()
+	async fn announce_at(&self) -> Option<f64> {
+		self.round.read().await.announce_at
 	}
 
-	/// Set the buzzing person to the specified.
+	/// Get the current question round.
 	#[inline]
-	pub async fn set_buzzed(&self, name: impl Into<Option<String>>) -> Option<&Self> {
-		let buzzed = name.into();
-		*self.buzzed.write().await = buzzed.clone();
-		self.event_pub.send(ServerMessage::Buzzed(buzzed)).ok();
+	pub async fn round(&self) -> Round {
+		self.round.read().await.to_round()
+	}
+
+	/// Get the time when the open round's buzzer locks in milliseconds since
+	/// the UNIX epoch. None means there is no open round with a countdown.
+	async fn round_deadline(&self) -> Option<f64> {
+		let round = self.round.read().await;
+		round.deadline.filter(|_| round.phase == RoundPhase::Open)
+	}
+
+	/// Set the phase of the round and publish the new round state.
+	async fn set_round_phase(&self, phase: RoundPhase) -> &Self {
+		let mut round = self.round.write().await;
+		round.phase = phase;
+		round.deadline = None;
+		self.publish(ServerMessage::Round(round.to_round()));
+		self
+	}
+
+	/// Arm the buzzer for a new question round, clearing the buzz queue. False
+	/// starts and lockouts are kept when arming from the idle phase, as they
+	/// belong to this question, otherwise only those of the false starts after
+	/// the previous question's buzzer locked. With a countdown, the buzzer
+	/// locks automatically when time runs out.
+	pub async fn open_round(&self, countdown: Option<Duration>) -> &Self {
+		let mut round = self.round.write().await;
+		let now = now_millis();
+		let mut false_starts = std::mem::take(&mut round.false_starts);
+		let mut lockouts = std::mem::take(&mut round.lockouts);
+		if round.phase != RoundPhase::Idle {
+			let carry_over = std::mem::take(&mut round.carry_over);
+			false_starts.retain(|id| carry_over.contains(id));
+			lockouts.retain(|id, _| carry_over.contains(id));
+		}
+		*round = RoundState {
+			phase: RoundPhase::Open,
+			opened_at: now,
+			deadline: countdown.map(|countdown| now + countdown.as_millis() as f64),
+			false_starts,
+			carry_over: HashSet::new(),
+			lockouts,
+			teams_buzzed: HashSet::new(),
+			announce_at: None,
+		};
+		let round_msg = ServerMessage::Round(round.to_round());
+		drop(round);
+
+		self.free_buzzer().await;
+		self.publish(round_msg);
+		self
+	}
+
+	/// Lock the buzzer of the open round. None means no round was open.
+	pub async fn lock_round(&self) -> Option<&Self> {
+		if self.round.read().await.phase != RoundPhase::Open {
+			return None;
+		}
+		Some(self.set_round_phase(RoundPhase::Locked).await)
+	}
+
+	/// Lock the buzzer of the open round if its deadline is the given one and
+	/// has passed.
+	async fn expire_round(&self, deadline: f64) -> &Self {
+		if self.round_deadline().await == Some(deadline) && deadline <= now_millis() {
+			self.set_round_phase(RoundPhase::Locked).await;
+		}
+		self
+	}
+
+	/// Reveal the answer of the current round, locking the buzzer.
+	pub async fn reveal_round(&self) -> &Self {
+		self.set_round_phase(RoundPhase::Revealed).await
+	}
+
+	/// End the current round and wait for the host to arm the buzzer again,
+	/// clearing the buzz queue, false starts and lockouts.
+	pub async fn reset_round(&self) -> &Self {
+		*self.round.write().await = RoundState::default();
+		self.free_buzzer().await;
+		self.publish(ServerMessage::Round(self.round().await));
+		self
+	}
+
+	/// Get whether teams are fixed, i.e. the buzzer is armed or locked.
+	/// Changing teams during a question would let a player buzz for a second
+	/// team.
+	pub async fn teams_fixed(&self) -> bool {
+		matches!(self.round.read().await.phase, RoundPhase::Open | RoundPhase::Locked)
+	}
+
+	/// Get whether the person is currently locked out from buzzing.
+	pub async fn is_locked_out(&self, id: Uuid) -> bool {
+		match self.round.read().await.lockouts.get(&id) {
+			Some(Some(until)) => *until > now_millis(),
+			Some(None) => true,
+			None => false,
+		}
+	}
+
+	/// Get the time when the next lockout ends in milliseconds since the UNIX
+	/// epoch.
+	async fn next_lockout_end(&self) -> Option<f64> {
+		self.round.read().await.lockouts.values().flatten().copied().reduce(f64::min)
+	}
+
+	/// Remove the lockouts that ended and publish the new round state if there
+	/// were any.
+	async fn end_lockouts(&self) -> &Self {
+		let mut round = self.round.write().await;
+		let now = now_millis();
+		let before = round.lockouts.len();
+		round.lockouts.retain(|_, until| until.is_none_or(|until| until > now));
+		if round.lockouts.len() != before {
+			self.publish(ServerMessage::Round(round.to_round()));
+		}
+		self
+	}
+
+	/// Publish the remaining time of the open round if it has a countdown.
+	async fn tick(&self) -> &Self {
+		if let Some(deadline) = self.round_deadline().await {
+			let remaining = (deadline - now_millis()).max(0.0).round();
+			self.publish_unsequenced(ServerMessage::Tick(remaining));
+		}
+		self
+	}
+
+	/// Spawn the task owning the clock of the room's rounds. It publishes a
+	/// tick every full second of the remaining time, locks the buzzer when
+	/// time runs out, announces the first buzz after its fairness window and
+	/// ends lockouts. Stops when the room is dropped.
+	pub fn spawn_round_timer(room: &Arc<Self>) {
+		let mut events = room.subscribe();
+		let room = Arc::downgrade(room);
+
+		tokio::spawn(async move {
+			loop {
+				let (deadline, lockout_end, announce_at) = match room.upgrade() {
+					Some(room) => (
+						room.round_deadline().await,
+						room.next_lockout_end().await,
+						room.announce_at().await,
+					),
+					None => break,
+				};
+				let now = now_millis();
+				let until_tick = deadline.map(|deadline| {
+					// Tick when the remaining time reaches the next full second.
+					let remaining = (deadline - now).max(0.0);
+					let full_seconds_after_tick = ((remaining / 1000.0).ceil() - 1.0).max(0.0);
+					full_seconds_after_tick.mul_add(-1000.0, remaining)
+				});
+				let until_lockout_end = lockout_end.map(|end| (end - now).max(0.0));
+				let until_announcement = announce_at.map(|at| (at - now).max(0.0));
+				let wait = until_tick
+					.into_iter()
+					.chain(until_lockout_end)
+					.chain(until_announcement)
+					.reduce(f64::min);
+				let tick_due =
+					until_tick.is_some_and(|until_tick| wait.is_none_or(|wait| until_tick <= wait));
+				let sleep = async {
+					match wait {
+						Some(wait) => {
+							tokio::time::sleep(Duration::from_micros((wait * 1000.0) as u64)).await;
+						}
+						None => future::pending().await,
+					}
+				};
+
+				tokio::select! {
+					event = events.recv() => {
+						if matches!(event, Err(RecvError::Closed)) {
+							break;
+						}
+					}
+					_ = sleep => {
+						let room = match room.upgrade() {
+							Some(room) => room,
+							None => break,
+						};
+						room.announce_buzz().await;
+						room.end_lockouts().await;
+						if let Some(deadline) = deadline.filter(|_| tick_due) {
+							if deadline <= now_millis() {
+								room.expire_round(deadline).await;
+							} else {
+								room.tick().await;
+							}
+						}
+					}
+				}
+			}
+		});
+	}
+
+	/// Get the scoreboard of the room.
+	#[inline]
+	pub async fn scores(&self) -> HashMap<Uuid, Score> {
+		self.scores.read().await.clone()
+	}
+
+	/// Award points to the specified person and their team. Negative points
+	/// deduct points. None means the person is neither in the room nor on the
+	/// scoreboard.
+	pub async fn award_points(&self, id: Uuid, points: i64) -> Option<&Self> {
+		let name = self.name_of(id).await?;
+		let team = self.team_of(id).await;
+
+		let mut scores = self.scores.write().await;
+		let score = scores.entry(id).or_default();
+		score.name = name.clone();
+		score.points = score.points.saturating_add(points);
+		let scores_msg = ServerMessage::Scores(scores.clone());
+		drop(scores);
+		self.publish(scores_msg);
+
+		if let Some(team) = team.as_ref() {
+			if let Some(score) = self.teams.write().await.get_mut(team) {
+				*score = score.saturating_add(points);
+			}
+			self.publish(ServerMessage::Teams(self.teams().await));
+		}
+
+		self.history.write().await.push(ScoreEntry { time: now_millis(), id, name, team, points });
+
+		Some(self)
+	}
+
+	/// Judge the answer of the current buzzing person. A correct answer awards
+	/// the configured points, frees the buzzer and reveals the round, an
+	/// incorrect answer deducts the configured points, locks the person and
+	/// their team out and passes on to the next buzzer. Returns the judged
+	/// person, None means nobody buzzed.
+	pub async fn judge_answer(&self, correct: bool) -> Option<Uuid> {
+		let buzzed = self.buzz_queue.read().await.first()?.id;
+
+		if correct {
+			self.award_points(buzzed, self.settings.points_correct).await;
+			self.free_buzzer().await;
+			self.reveal_round().await;
+		} else {
+			self.award_points(buzzed, self.settings.points_incorrect.saturating_neg()).await;
+			let team = self.team_of(buzzed).await;
+			let teammates = match team {
+				Some(team) => self.teams().await.remove(&team).map(|team| team.members),
+				None => None,
+			};
+			let mut round = self.round.write().await;
+			for id in teammates.into_iter().flatten().chain([buzzed]) {
+				round.lock_out(id, self.settings.wrong_answer_lockout);
+			}
+			drop(round);
+			self.next_buzzer().await;
+			self.publish(ServerMessage::Round(self.round().await));
+		}
+
+		Some(buzzed)
+	}
+
+	/// Get the teams of the room with their members and points.
+	pub async fn teams(&self) -> BTreeMap<String, Team> {
+		let members = self.members.read().await;
+		let mut teams: BTreeMap<String, Team> = self
+			.teams
+			.read()
+			.await
+			.iter()
+			.map(|(name, &score)| (name.clone(), Team { members: Vec::new(), score }))
+			.collect();
+
+		for member in members.values() {
+			if let Some(team) = member.team.as_ref().and_then(|team| teams.get_mut(team)) {
+				team.members.push(member.user.id);
+			}
+		}
+
+		teams
+	}
+
+	/// Create a new team. None means the name was invalid or the team already
+	/// exists.
+	pub async fn create_team(&self, team: &str) -> Option<&Self> {
+		let team = team.trim();
+		if team.is_empty() {
+			return None;
+		}
+
+		let mut teams = self.teams.write().await;
+		if teams.contains_key(team) {
+			return None;
+		}
+		teams.insert(team.to_owned(), 0);
+		drop(teams);
+
+		self.publish(ServerMessage::Teams(self.teams().await));
+		Some(self)
+	}
+
+	/// Remove a team, its members are left without team. None means the team
+	/// did not exist.
+	pub async fn remove_team(&self, team: &str) -> Option<&Self> {
+		let mut members = self.members.write().await;
+		self.teams.write().await.remove(team)?;
+
+		for member in members.values_mut() {
+			if member.team.as_deref() == Some(team) {
+				member.team = None;
+			}
+		}
+		drop(members);
+
+		self.publish(ServerMessage::Teams(self.teams().await));
+		Some(self)
+	}
+
+	/// Set the team of a member, None removes the member from their team. None
+	/// is returned if the member or team does not exist.
+	pub async fn set_team(&self, id: Uuid, team: Option<String>) -> Option<&Self> {
+		let mut members = self.members.write().await;
+		if let Some(team) = team.as_ref() {
+			if !self.teams.read().await.contains_key(team) {
+				return None;
+			}
+		}
+		members.get_mut(&id)?.team = team;
+		drop(members);
+
+		self.publish(ServerMessage::Teams(self.teams().await));
 		Some(self)
 	}
 
 	/// Get state of the room
 	pub async fn state(&self) -> websocket::RoomState {
 		websocket::RoomState {
-			members: self.members().await.into_keys().collect(),
+			members: self
+				.members()
+				.await
+				.into_values()
+				.map(|member| websocket::Member {
+					id: member.user.id,
+					name: member.user.name,
+					connected: member.connections > 0,
+				})
+				.collect(),
 			host: self.host().await,
-			buzzed: self.buzzed().await,
+			buzz_queue: self.buzz_queue().await,
+			scores: self.scores().await,
+			teams: self.teams().await,
+			round: self.round().await,
+			spectators: self.num_spectators(),
 		}
 	}
 
-	/// Join a new member if it doesn't exist and return the current amount of
-	/// members after that operation if successful. None means somebody with
-	/// that name was already present.
+	/// Join a new member or add a connection to an existing member and return
+	/// the current amount of members after that operation if successful. None
+	/// means the room was closed.
 	pub async fn join_member(&self, user: UserData) -> Option<usize> {
 		let mut members = self.members.write().await;
-		if members.get(&user.name).is_some() {
+		if self.is_closed() {
 			return None;
 		}
 
-		if members.len() == 0 {
-			self.set_host(&user.name).await;
+		if members.is_empty() {
+			self.set_host(Some(user.id)).await;
+		}
+		if let Some(score) = self.scores.write().await.get_mut(&user.id) {
+			score.name = user.name.clone();
+		}
+		match members.get_mut(&user.id) {
+			Some(member) => {
+				member.user.name = user.name;
+				member.connections += 1;
+				member.disconnected_at = None;
+			}
+			None => {
+				let member = Member {
+					user,
+					team: None,
+					connections: 1,
+					disconnected_at: None,
+					sessions: HashMap::new(),
+				};
+				members.insert(member.user.id, member);
+			}
 		}
-		members.insert(user.name.clone(), user);
 		let num_members = members.len();
 		drop(members);
+		*self.last_active.write().await = now_millis();
 
-		self.event_pub.send(ServerMessage::State(self.state().await)).ok();
+		self.publish(ServerMessage::State(self.state().await));
 
 		Some(num_members)
 	}
 
-	/// Leave a member if it is there and return the current amount of members
-	/// after that operation if successful. None means it was not there to
-	/// leave.
-	pub async fn leave_member(&self, name: &String) -> Option<usize> {
+	/// Add a spectator to the room. Returns the number of spectators, None
+	/// means the room was closed.
+	pub async fn join_spectator(&self) -> Option<usize> {
+		if self.is_closed() {
+			return None;
+		}
+		let spectators = self.spectators.fetch_add(1, Ordering::SeqCst) + 1;
+		*self.last_active.write().await = now_millis();
+		self.publish(ServerMessage::State(self.state().await));
+		Some(spectators)
+	}
+
+	/// Remove a spectator from the room and return the remaining number of
+	/// spectators.
+	pub async fn leave_spectator(&self) -> usize {
+		let spectators = self.spectators.fetch_sub(1, Ordering::SeqCst).saturating_sub(1);
+		*self.last_active.write().await = now_millis();
+		self.publish(ServerMessage::State(self.state().await));
+		spectators
+	}
+
+	/// Change the display name of a member, also on the scoreboard. Names are
+	/// validated like at login. None means the name was invalid or the member
+	/// was not in the room.
+	pub async fn rename_member(&self, id: Uuid, name: &str) -> Option<&Self> {
+		if !(LoginInfo { username: name.to_owned() }).is_valid() {
+			return None;
+		}
+		let name = name.trim();
+
 		let mut members = self.members.write().await;
-		let prev = members.remove(name);
+		members.get_mut(&id)?.user.name = name.to_owned();
+		if let Some(score) = self.scores.write().await.get_mut(&id) {
+			score.name = name.to_owned();
+		}
+		drop(members);
 
-		if let Some(prev) = prev.as_ref() {
-			if prev.name == self.host().await {
-				let first = members.iter().next().map_or("", |(_, user)| &user.name);
-				self.set_host(first).await;
+		self.publish(ServerMessage::State(self.state().await));
+		Some(self)
+	}
+
+	/// Resume the member's session with the given resume token or start a new
+	/// one if the token is unknown, dropping the least recently used session
+	/// if the member has too many. Returns the session's resume token and
+	/// last latency measurement, None means the member is not in the room.
+	pub async fn resume_session(
+		&self,
+		id: Uuid,
+		token: Option<Uuid>,
+	) -> Option<(Uuid, Option<Latency>)> {
+		let mut members = self.members.write().await;
+		let sessions = &mut members.get_mut(&id)?.sessions;
+		let now = now_millis();
+		if let Some((token, session)) =
+			token.and_then(|token| Some((token, sessions.get_mut(&token)?)))
+		{
+			session.used_at = now;
+			return Some((token, session.latency));
+		}
+
+		if sessions.len() >= MAX_SESSIONS {
+			let oldest = sessions
+				.iter()
+				.min_by(|(_, a), (_, b)| a.used_at.total_cmp(&b.used_at))
+				.map(|(&token, _)| token);
+			if let Some(oldest) = oldest {
+				sessions.remove(&oldest);
 			}
 		}
+		let token = Uuid::new_v4();
+		sessions.insert(token, Session { latency: None, used_at: now });
+		Some((token, None))
+	}
+
+	/// Save the latency measurement of the member's session for resuming it,
+	/// unless the session was dropped meanwhile.
+	pub async fn save_session(&self, id: Uuid, token: Uuid, latency: Option<Latency>) -> &Self {
+		let mut members = self.members.write().await;
+		if let Some(session) =
+			members.get_mut(&id).and_then(|member| member.sessions.get_mut(&token))
+		{
+			session.latency = latency;
+		}
+		self
+	}
+
+	/// Close a connection of a member and return the current amount of members
+	/// after that operation if successful. When their last connection closes,
+	/// the member is marked as disconnected but keeps their seat until
+	/// [`Self::expire_member`]. None means it was not there to leave.
+	pub async fn leave_member(&self, id: Uuid) -> Option<usize> {
+		let mut members = self.members.write().await;
+		let member = members.get_mut(&id)?;
+		member.connections = member.connections.saturating_sub(1);
+		if member.connections == 0 {
+			member.disconnected_at = Some(now_millis());
+		}
+		let num_members = members.len();
+		drop(members);
+		*self.last_active.write().await = now_millis();
+
+		self.publish(ServerMessage::State(self.state().await));
+
+		Some(num_members)
+	}
+
+	/// Get the disconnected members with the time when their last connection
+	/// closed in milliseconds since the UNIX epoch.
+	pub async fn disconnected_members(&self) -> Vec<(Uuid, f64)> {
+		self.members
+			.read()
+			.await
+			.iter()
+			.filter_map(|(&id, member)| Some((id, member.disconnected_at?)))
+			.collect()
+	}
+
+	/// Get the time when the member's last connection closed in milliseconds
+	/// since the UNIX epoch. None means the member is connected or not in the
+	/// room.
+	pub async fn disconnected_since(&self, id: Uuid) -> Option<f64> {
+		self.members.read().await.get(&id)?.disconnected_at
+	}
+
+	/// Remove a disconnected member if they did not reconnect since the given
+	/// time of disconnecting. Returns the current amount of members after that
+	/// operation, None means the member reconnected or was not there.
+	pub async fn expire_member(&self, id: Uuid, disconnected_at: f64) -> Option<usize> {
+		let members = self.members.write().await;
+		if members.get(&id)?.disconnected_at != Some(disconnected_at) {
+			return None;
+		}
+		self.remove_member(members, id).await
+	}
+
+	/// Remove a member with all their connections and return the current
+	/// amount of members after that operation. None means it was not there to
+	/// remove.
+	async fn remove_member(
+		&self,
+		mut members: RwLockWriteGuard<'_, HashMap<Uuid, Member>>,
+		id: Uuid,
+	) -> Option<usize> {
+		let prev = members.remove(&id);
+
+		if prev.is_some() && self.host().await == Some(id) {
+			// Prefer connected members as new host.
+			let host = members
+				.values()
+				.find(|member| member.connections > 0)
+				.or_else(|| members.values().next())
+				.map(|member| member.user.id);
+			self.set_host(host).await;
+		}
 
 		let out = prev.map(|_old| members.len());
 		drop(members);
+		*self.last_active.write().await = now_millis();
+
+		self.buzz_queue.write().await.retain(|buzz| buzz.id != id);
 
-		self.event_pub.send(ServerMessage::State(self.state().await)).ok();
+		self.publish(ServerMessage::State(self.state().await));
 
 		out
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::{sync::Arc, time::Duration};
+
+	use api_types::MAX_NAME_LENGTH;
+	use tokio::task::JoinError;
+	use uuid::Uuid;
+
+	use super::{
+		now_millis, Latency, Lockout, PasswordHash, RoomAccess, RoomSettings, RoomState,
+		RoundPhase, RoundState, ServerMessage, UserData, MAX_SESSIONS,
+	};
+
+	/// Create a room with the default settings and an open round, which was
+	/// armed ten seconds ago so that the fairness window is not limited by the
+	/// time of arming.
+	async fn room() -> RoomState {
+		let room = RoomState::new(RoomSettings::default(), RoomAccess::default());
+		room.open_round(None).await;
+		room.round.write().await.opened_at -= 10_000.0;
+		room
+	}
+
+	/// Create a user with a new ID.
+	fn user(name: &str) -> UserData {
+		UserData { id: Uuid::new_v4(), name: name.to_owned() }
+	}
+
+	/// Join a member to the room, in the given team if any.
+	async fn join(room: &RoomState, user: &UserData, team: Option<&str>) {
+		room.join_member(user.clone()).await;
+		if let Some(team) = team {
+			room.create_team(team).await;
+			room.set_team(user.id, Some(team.to_owned())).await;
+		}
+	}
+
+	/// Get the user IDs and times of the buzz queue.
+	async fn queue(room: &RoomState) -> Vec<(Uuid, f64)> {
+		room.buzz_queue.read().await.iter().map(|buzz| (buzz.id, buzz.time)).collect()
+	}
+
+	/// Get the user IDs of the buzz queue.
+	async fn ids(room: &RoomState) -> Vec<Uuid> {
+		queue(room).await.into_iter().map(|(id, _)| id).collect()
+	}
+
+	/// Buzzes are queued in order of arrival without estimate, and everyone
+	/// can buzz once.
+	#[tokio::test]
+	async fn buzz_queue() {
+		let room = room().await;
+		let (alice, bob) = (user("Alice"), user("Bob"));
+		assert_eq!(room.buzz(&alice, None, None).await, Some(0));
+		assert_eq!(room.buzz(&bob, None, None).await, Some(1));
+		assert_eq!(room.buzz(&alice, None, None).await, None);
+		assert_eq!(ids(&room).await, [alice.id, bob.id]);
+	}
+
+	/// Buzzes are ordered by their estimated press time, not their arrival.
+	#[tokio::test]
+	async fn buzz_orders_by_press_time() {
+		let room = room().await;
+		let (alice, bob, carol) = (user("Alice"), user("Bob"), user("Carol"));
+		let now = now_millis();
+		assert_eq!(room.buzz(&alice, None, None).await, Some(0));
+		assert_eq!(room.buzz(&bob, Some(now - 100.0), None).await, Some(0));
+		assert_eq!(room.buzz(&carol, Some(now - 50.0), None).await, Some(1));
+		assert_eq!(ids(&room).await, [bob.id, carol.id, alice.id]);
+	}
+
+	/// Press times are limited to the fairness window before the arrival.
+	#[tokio::test]
+	async fn buzz_clamps_to_fairness_window() {
+		let room = room().await;
+		let window = room.settings.fairness_window_ms as f64;
+
+		let before = now_millis();
+		room.buzz(&user("Alice"), Some(before - 5000.0), None).await;
+		let after = now_millis();
+
+		let (_, time) = queue(&room).await[0];
+		assert!(time >= before - window && time <= after - window);
+	}
+
+	/// Press times in the future and invalid times are limited to the arrival.
+	#[tokio::test]
+	async fn buzz_clamps_to_arrival() {
+		let room = room().await;
+
+		let before = now_millis();
+		room.buzz(&user("Alice"), Some(before + 5000.0), None).await;
+		room.buzz(&user("Bob"), Some(f64::NAN), None).await;
+		let after = now_millis();
+
+		for (_, time) in queue(&room).await {
+			assert!(time >= before && time <= after);
+		}
+	}
+
+	/// Press times are limited to the time of arming the buzzer.
+	#[tokio::test]
+	async fn buzz_clamps_to_opening() {
+		let room = RoomState::new(RoomSettings::default(), RoomAccess::default());
+		room.open_round(None).await;
+		let opened_at = room.round.read().await.opened_at;
+
+		room.buzz(&user("Alice"), Some(0.0), None).await;
+
+		let (_, time) = queue(&room).await[0];
+		assert!((time - opened_at).abs() < f64::EPSILON);
+	}
+
+	/// Passing on removes the current buzzer until the queue is empty.
+	#[tokio::test]
+	async fn next_buzzer() {
+		let room = room().await;
+		let (alice, bob) = (user("Alice"), user("Bob"));
+		room.buzz(&alice, None, None).await;
+		room.buzz(&bob, None, None).await;
+
+		assert_eq!(room.next_buzzer().await, Some(Some(bob.id)));
+		assert_eq!(ids(&room).await, [bob.id]);
+		assert_eq!(room.next_buzzer().await, Some(None));
+		assert!(ids(&room).await.is_empty());
+		assert_eq!(room.next_buzzer().await, None);
+	}
+
+	/// Freeing the buzzer clears the queue, so everyone can buzz again.
+	#[tokio::test]
+	async fn free_buzzer() {
+		let room = room().await;
+		let alice = user("Alice");
+		room.buzz(&alice, None, None).await;
+		room.free_buzzer().await;
+
+		assert!(ids(&room).await.is_empty());
+		assert_eq!(room.buzz(&alice, None, None).await, Some(0));
+	}
+
+	/// Only one member of each team can buzz, members without team buzz on
+	/// their own.
+	#[tokio::test]
+	async fn team_buzzes_once() {
+		let room = room().await;
+		let [alice, bob, carol, dave, eve] = ["Alice", "Bob", "Carol", "Dave", "Eve"].map(user);
+		join(&room, &alice, Some("Red")).await;
+		join(&room, &bob, Some("Red")).await;
+		join(&room, &carol, Some("Blue")).await;
+		join(&room, &dave, None).await;
+		join(&room, &eve, None).await;
+
+		assert_eq!(room.buzz(&alice, None, None).await, Some(0));
+		assert_eq!(room.buzz(&bob, None, None).await, None);
+		assert_eq!(room.buzz(&carol, None, None).await, Some(1));
+		assert_eq!(room.buzz(&dave, None, None).await, Some(2));
+		assert_eq!(room.buzz(&eve, None, None).await, Some(3));
+	}
+
+	/// A team keeps its single attempt after its buzz was passed on, until the
+	/// buzzer is freed.
+	#[tokio::test]
+	async fn team_buzzes_once_per_question() {
+		let room = room().await;
+		let [alice, bob] = ["Alice", "Bob"].map(user);
+		join(&room, &alice, Some("Red")).await;
+		join(&room, &bob, Some("Red")).await;
+
+		room.buzz(&alice, None, None).await;
+		room.next_buzzer().await;
+		assert_eq!(room.buzz(&bob, None, None).await, None);
+
+		room.free_buzzer().await;
+		assert_eq!(room.buzz(&bob, None, None).await, Some(0));
+	}
+
+	/// Teams are fixed while the buzzer is armed or locked.
+	#[tokio::test]
+	async fn teams_fixed() {
+		let room = room().await;
+		assert!(room.teams_fixed().await);
+		room.lock_round().await;
+		assert!(room.teams_fixed().await);
+		room.reveal_round().await;
+		assert!(!room.teams_fixed().await);
+		room.reset_round().await;
+		assert!(!room.teams_fixed().await);
+	}
+
+	/// Members of removed teams are left without team.
+	#[tokio::test]
+	async fn remove_team() {
+		let room = room().await;
+		let (alice, bob) = (user("Alice"), user("Bob"));
+		join(&room, &alice, Some("Red")).await;
+		join(&room, &bob, Some("Red")).await;
+
+		assert!(room.remove_team("Red").await.is_some());
+		assert_eq!(room.team_of(alice.id).await, None);
+		assert!(room.teams().await.is_empty());
+		assert_eq!(room.buzz(&alice, None, None).await, Some(0));
+		assert_eq!(room.buzz(&bob, None, None).await, Some(1));
+	}
+
+	/// Members can only join existing teams.
+	#[tokio::test]
+	async fn set_team_requires_team() {
+		let room = room().await;
+		let alice = user("Alice");
+		join(&room, &alice, None).await;
+
+		assert!(room.set_team(alice.id, Some("Red".to_owned())).await.is_none());
+		assert!(room.create_team(" ").await.is_none());
+		assert!(room.create_team("Red").await.is_some());
+		assert!(room.create_team("Red").await.is_none());
+		assert!(room.set_team(Uuid::new_v4(), Some("Red".to_owned())).await.is_none());
+		assert!(room.set_team(alice.id, Some("Red".to_owned())).await.is_some());
+		assert_eq!(room.teams().await["Red"].members, [alice.id]);
+	}
+
+	/// Judging an answer scores the person and their team.
+	#[tokio::test]
+	async fn judge_answer_scores_team() {
+		let room = room().await;
+		let settings = RoomSettings::default();
+		let (alice, bob) = (user("Alice"), user("Bob"));
+		join(&room, &alice, Some("Red")).await;
+		join(&room, &bob, None).await;
+
+		room.buzz(&bob, None, None).await;
+		room.buzz(&alice, None, None).await;
+		assert_eq!(room.judge_answer(false).await, Some(bob.id));
+		assert_eq!(room.judge_answer(true).await, Some(alice.id));
+		assert_eq!(room.judge_answer(true).await, None);
+
+		let scores = room.scores().await;
+		assert_eq!(scores[&alice.id].points, settings.points_correct);
+		assert_eq!(scores[&bob.id].points, -settings.points_incorrect);
+		assert_eq!(room.teams().await["Red"].score, settings.points_correct);
+	}
+
+	/// Restoring a snapshot keeps scores, teams, history, the host and the
+	/// members, who are disconnected until they reconnect.
+	#[tokio::test]
+	async fn snapshot_restore() -> Result<(), serde_json::Error> {
+		let room = room().await;
+		let (alice, bob) = (user("Alice"), user("Bob"));
+		join(&room, &alice, Some("Red")).await;
+		join(&room, &bob, Some("Blue")).await;
+		room.transfer_host(bob.id).await;
+		room.award_points(alice.id, 3).await;
+		room.award_points(bob.id, -1).await;
+
+		let snapshot = serde_json::to_string(&room.snapshot().await)?;
+		let restored = RoomState::from_snapshot(serde_json::from_str(&snapshot)?);
+
+		assert_eq!(restored.scores().await, room.scores().await);
+		assert_eq!(restored.teams().await, room.teams().await);
+		assert_eq!(restored.history.read().await.len(), 2);
+		assert!((restored.last_active().await - room.last_active().await).abs() < 1.0);
+		assert_eq!(restored.host().await, Some(bob.id));
+		assert_eq!(restored.team_of(alice.id).await.as_deref(), Some("Red"));
+		assert_eq!(restored.disconnected_members().await.len(), 2);
+
+		join(&restored, &alice, None).await;
+		assert_eq!(restored.disconnected_since(alice.id).await, None);
+		Ok(())
+	}
+
+	/// The first member hosts the room, and the host role can be transferred
+	/// to members only.
+	#[tokio::test]
+	async fn transfer_host() {
+		let room = room().await;
+		let (alice, bob) = (user("Alice"), user("Bob"));
+		join(&room, &alice, None).await;
+		join(&room, &bob, None).await;
+		assert_eq!(room.host().await, Some(alice.id));
+
+		assert!(room.transfer_host(Uuid::new_v4()).await.is_none());
+		assert!(room.transfer_host(bob.id).await.is_some());
+		assert_eq!(room.host().await, Some(bob.id));
+
+		room.leave_member(bob.id).await;
+		assert_eq!(room.host().await, Some(bob.id));
+		let disconnected_at = room.disconnected_since(bob.id).await.unwrap_or_default();
+		room.expire_member(bob.id, disconnected_at).await;
+		assert_eq!(room.host().await, Some(alice.id));
+	}
+
+	/// Kicked members leave the room and the buzz queue.
+	#[tokio::test]
+	async fn kick() {
+		let room = room().await;
+		let (alice, bob) = (user("Alice"), user("Bob"));
+		join(&room, &alice, None).await;
+		join(&room, &bob, None).await;
+		room.buzz(&bob, None, None).await;
+
+		assert!(room.kick(bob.id, None).await.is_some());
+		assert!(!room.members().await.contains_key(&bob.id));
+		assert!(ids(&room).await.is_empty());
+		assert!(!room.is_banned(&bob).await);
+	}
+
+	/// Kicking somebody who is not in the room is rejected without notice.
+	#[tokio::test]
+	async fn kick_unknown() {
+		let room = room().await;
+		let mut events = room.subscribe();
+		assert!(room.kick(user("Alice").id, None).await.is_none());
+		while let Ok(event) = events.try_recv() {
+			assert!(!matches!(event.message, ServerMessage::Kicked { .. }));
+		}
+	}
+
+	/// Banned persons are kicked and stay banned.
+	#[tokio::test]
+	async fn ban() {
+		let room = room().await;
+		let (alice, bob, carol) = (user("Alice"), user("Bob"), user("Carol"));
+		join(&room, &alice, None).await;
+		join(&room, &bob, None).await;
+
+		room.ban(bob.id, Some("Spoilers".to_owned())).await;
+		room.ban(carol.id, None).await;
+		assert!(!room.members().await.contains_key(&bob.id));
+		assert!(room.is_banned(&bob).await);
+		assert!(room.is_banned(&carol).await);
+		assert!(!room.is_banned(&alice).await);
+	}
+
+	/// Bans also apply to the banned member's name, so logging in again under
+	/// the same name does not help.
+	#[tokio::test]
+	async fn ban_by_name() -> Result<(), serde_json::Error> {
+		let room = room().await;
+		let bob = user("Bob");
+		join(&room, &bob, None).await;
+		room.ban(bob.id, None).await;
+
+		assert!(room.is_banned(&user(" bob ")).await);
+		assert!(!room.is_banned(&user("Bobby")).await);
+
+		let snapshot = serde_json::to_string(&room.snapshot().await)?;
+		let restored = RoomState::from_snapshot(serde_json::from_str(&snapshot)?);
+		assert!(restored.is_banned(&bob).await);
+		assert!(restored.is_banned(&user("BOB")).await);
+		Ok(())
+	}
+
+	/// Rooms with password require the password or an invite.
+	#[tokio::test]
+	async fn access_password() -> Result<(), JoinError> {
+		let access = RoomAccess {
+			password_hash: Some(PasswordHash::new("secret")),
+			..RoomAccess::default()
+		};
+		assert!(access.allows(Some("secret"), false));
+		assert!(!access.allows(Some("wrong"), false));
+		assert!(!access.allows(None, false));
+		assert!(access.allows(None, true));
+
+		assert!(access.allows_blocking(Some("secret".to_owned()), false).await?);
+
+		let info = access.info(Some("wrong".to_owned()), false).await?;
+		assert!(info.password && !info.granted);
+		Ok(())
+	}
+
+	/// Invite-only rooms require an invite.
+	#[test]
+	fn access_invite_only() {
+		let access = RoomAccess { invite_only: true, ..RoomAccess::default() };
+		assert!(!access.allows(None, false));
+		assert!(access.allows(None, true));
+		assert!(RoomAccess::default().allows(None, false));
+	}
+
+	/// Buzzes are only accepted while the buzzer is armed.
+	#[tokio::test]
+	async fn round_phases() {
+		let room = room().await;
+		let alice = user("Alice");
+		assert!(room.lock_round().await.is_some());
+		assert_eq!(room.round().await.phase, RoundPhase::Locked);
+		assert_eq!(room.buzz(&alice, None, None).await, None);
+		assert!(room.lock_round().await.is_none());
+
+		room.reveal_round().await;
+		assert_eq!(room.round().await.phase, RoundPhase::Revealed);
+		assert_eq!(room.buzz(&alice, None, None).await, None);
+
+		room.open_round(None).await;
+		assert_eq!(room.round().await.phase, RoundPhase::Open);
+		assert_eq!(room.buzz(&alice, None, None).await, Some(0));
+
+		room.reset_round().await;
+		assert_eq!(room.round().await.phase, RoundPhase::Idle);
+		assert!(ids(&room).await.is_empty());
+	}
+
+	/// Buzzing before the buzzer is armed is a false start of the next round.
+	#[tokio::test]
+	async fn false_starts() {
+		let room = room().await;
+		let alice = user("Alice");
+		room.reset_round().await;
+
+		assert_eq!(room.buzz(&alice, None, None).await, None);
+		assert_eq!(room.buzz(&alice, None, None).await, None);
+		assert_eq!(room.round().await.false_starts, [alice.id]);
+
+		room.open_round(None).await;
+		assert_eq!(room.round().await.false_starts, [alice.id]);
+		room.lock_round().await;
+		room.open_round(None).await;
+		assert!(room.round().await.false_starts.is_empty());
+
+		room.buzz(&alice, None, None).await;
+		room.reset_round().await;
+		assert!(room.round().await.false_starts.is_empty());
+	}
+
+	/// The buzzer locks when the countdown runs out.
+	#[tokio::test]
+	async fn countdown() {
+		let room = Arc::new(RoomState::new(RoomSettings::default(), RoomAccess::default()));
+		RoomState::spawn_round_timer(&room);
+		room.open_round(Some(Duration::from_millis(50))).await;
+		assert!(room.round().await.deadline.is_some());
+
+		tokio::time::sleep(Duration::from_millis(500)).await;
+		let round = room.round().await;
+		assert_eq!(round.phase, RoundPhase::Locked);
+		assert_eq!(round.deadline, None);
+		assert_eq!(room.buzz(&user("Alice"), None, None).await, None);
+	}
+
+	/// False starts lock the person out, also after arming the buzzer.
+	#[tokio::test]
+	async fn false_start_lockout() {
+		let room = room().await;
+		let alice = user("Alice");
+		room.reset_round().await;
+		room.buzz(&alice, None, None).await;
+		assert!(room.is_locked_out(alice.id).await);
+
+		room.open_round(None).await;
+		assert!(room.is_locked_out(alice.id).await);
+		assert!(!room.is_locked_out(Uuid::new_v4()).await);
+		assert!(room.round().await.locked_out.contains_key(&alice.id));
+	}
+
+	/// Wrong answers lock the person out for the rest of the question.
+	#[tokio::test]
+	async fn wrong_answer_lockout() {
+		let room = room().await;
+		let alice = user("Alice");
+		room.buzz(&alice, None, None).await;
+		room.judge_answer(false).await;
+		assert!(room.is_locked_out(alice.id).await);
+		assert_eq!(room.round().await.locked_out[&alice.id], None);
+
+		room.open_round(None).await;
+		assert!(!room.is_locked_out(alice.id).await);
+	}
+
+	/// A wrong answer locks out the whole team.
+	#[tokio::test]
+	async fn wrong_answer_locks_out_team() {
+		let room = room().await;
+		let [alice, bob, carol] = ["Alice", "Bob", "Carol"].map(user);
+		join(&room, &alice, Some("Red")).await;
+		join(&room, &bob, Some("Red")).await;
+		join(&room, &carol, Some("Blue")).await;
+
+		room.buzz(&alice, None, None).await;
+		room.judge_answer(false).await;
+		assert!(room.is_locked_out(alice.id).await);
+		assert!(room.is_locked_out(bob.id).await);
+		assert!(!room.is_locked_out(carol.id).await);
+	}
+
+	/// Longer lockouts are kept, lockouts can be disabled.
+	#[test]
+	fn lock_out_keeps_longer() {
+		let mut round = RoundState::default();
+		let id = Uuid::new_v4();
+		round.lock_out(id, Lockout::Off);
+		assert!(round.lockouts.is_empty());
+
+		round.lock_out(id, Lockout::Millis(60_000));
+		round.lock_out(id, Lockout::Millis(10));
+		assert!(round.lockouts[&id].is_some_and(|until| until > now_millis() + 50_000.0));
+
+		round.lock_out(id, Lockout::RestOfQuestion);
+		round.lock_out(id, Lockout::Millis(10));
+		assert_eq!(round.lockouts[&id], None);
+	}
+
+	/// Timed lockouts end by themselves.
+	#[tokio::test]
+	async fn lockout_ends() {
+		let settings =
+			RoomSettings { false_start_lockout: Lockout::Millis(50), ..RoomSettings::default() };
+		let room = Arc::new(RoomState::new(settings, RoomAccess::default()));
+		let alice = user("Alice");
+		RoomState::spawn_round_timer(&room);
+		room.buzz(&alice, None, None).await;
+		assert!(room.is_locked_out(alice.id).await);
+
+		tokio::time::sleep(Duration::from_millis(500)).await;
+		assert!(!room.is_locked_out(alice.id).await);
+		assert!(room.round().await.locked_out.is_empty());
+	}
+
+	/// Members are identified by ID, so names can repeat and change.
+	#[tokio::test]
+	async fn members_by_id() {
+		let room = room().await;
+		let (first, second) = (user("Alice"), user("Alice"));
+		join(&room, &first, None).await;
+		join(&room, &second, None).await;
+		room.award_points(first.id, 1).await;
+		assert_eq!(room.num_members().await, 2);
+
+		assert!(room.rename_member(first.id, " ").await.is_none());
+		assert!(room.rename_member(first.id, &"A".repeat(MAX_NAME_LENGTH + 1)).await.is_none());
+		assert!(room.rename_member(first.id, " Alicia ").await.is_some());
+		assert_eq!(room.name_of(first.id).await.as_deref(), Some("Alicia"));
+		assert_eq!(room.name_of(second.id).await.as_deref(), Some("Alice"));
+		assert_eq!(room.scores().await[&first.id].name, "Alicia");
+	}
+
+	/// Disconnected members keep their seat until they expire, unless they
+	/// reconnect.
+	#[tokio::test]
+	async fn disconnected_member_keeps_seat() {
+		let room = room().await;
+		let alice = user("Alice");
+		join(&room, &alice, None).await;
+		room.buzz(&alice, None, None).await;
+
+		room.leave_member(alice.id).await;
+		let disconnected_at = room.disconnected_since(alice.id).await.unwrap_or_default();
+		assert!(disconnected_at > 0.0);
+		assert_eq!(ids(&room).await, [alice.id]);
+
+		join(&room, &alice, None).await;
+		assert_eq!(room.disconnected_since(alice.id).await, None);
+		assert_eq!(room.expire_member(alice.id, disconnected_at).await, None);
+		assert_eq!(room.num_members().await, 1);
+
+		room.leave_member(alice.id).await;
+		let disconnected_at = room.disconnected_since(alice.id).await.unwrap_or_default();
+		assert_eq!(room.expire_member(alice.id, disconnected_at).await, Some(0));
+		assert!(ids(&room).await.is_empty());
+	}
+
+	/// Sessions are resumed by their token with the saved latency.
+	#[tokio::test]
+	async fn resume_session() {
+		let room = room().await;
+		let alice = user("Alice");
+		join(&room, &alice, None).await;
+		assert!(room.resume_session(Uuid::new_v4(), None).await.is_none());
+
+		let (token, latency) = room.resume_session(alice.id, None).await.unwrap_or_default();
+		assert!(latency.is_none());
+		room.save_session(alice.id, token, Some(Latency { rtt: 40.0, clock_offset: 5.0 })).await;
+
+		let resumed = room.resume_session(alice.id, Some(token)).await;
+		assert!(resumed.is_some_and(|(resumed, latency)| resumed == token
+			&& latency.is_some_and(|latency| (latency.rtt - 40.0).abs() < f64::EPSILON)));
+		let other = room.resume_session(alice.id, Some(Uuid::new_v4())).await;
+		assert!(other.is_some_and(|(other, latency)| other != token && latency.is_none()));
+	}
+
+	/// The first buzz is announced once, after its fairness window has passed,
+	/// so that later buzzes pressed earlier can still take its place.
+	#[tokio::test]
+	async fn buzz_announced_after_fairness_window() {
+		let room = Arc::new(room().await);
+		RoomState::spawn_round_timer(&room);
+		let mut events = room.subscribe();
+		let (alice, bob) = (user("Alice"), user("Bob"));
+
+		room.buzz(&alice, None, None).await;
+		room.buzz(&bob, Some(now_millis() - 100.0), None).await;
+		assert!(room.announce_at().await.is_some());
+		assert!(room.buzz_queue().await.is_empty());
+		assert!(events.try_recv().is_err());
+
+		tokio::time::sleep(Duration::from_millis(500)).await;
+		let mut messages = Vec::new();
+		while let Ok(event) = events.try_recv() {
+			messages.push(event.message);
+		}
+		let buzzed: Vec<_> = messages
+			.iter()
+			.filter_map(|message| match message {
+				ServerMessage::Buzzed(entry) => Some(entry.as_ref().map(|entry| entry.id)),
+				_ => None,
+			})
+			.collect();
+		assert_eq!(buzzed, [Some(bob.id)]);
+		assert_eq!(room.announce_at().await, None);
+		let queue: Vec<_> = room.buzz_queue().await.into_iter().map(|entry| entry.id).collect();
+		assert_eq!(queue, [bob.id, alice.id]);
+	}
+
+	/// The number of sessions is limited, dropping the least recently used.
+	#[tokio::test]
+	async fn session_limit() {
+		let room = room().await;
+		let alice = user("Alice");
+		join(&room, &alice, None).await;
+
+		let mut tokens = Vec::new();
+		for _ in 0..MAX_SESSIONS {
+			let (token, _) = room.resume_session(alice.id, None).await.unwrap_or_default();
+			tokens.push(token);
+		}
+		// Make the order of use unambiguous.
+		if let Some(member) = room.members.write().await.get_mut(&alice.id) {
+			for (used_at, token) in tokens.iter().enumerate() {
+				if let Some(session) = member.sessions.get_mut(token) {
+					session.used_at = used_at as f64;
+				}
+			}
+		}
+
+		room.resume_session(alice.id, Some(tokens[0])).await;
+		let (token, _) = room.resume_session(alice.id, None).await.unwrap_or_default();
+
+		let sessions = room.members().await[&alice.id].sessions.clone();
+		assert_eq!(sessions.len(), MAX_SESSIONS);
+		assert!(sessions.contains_key(&tokens[0]) && sessions.contains_key(&token));
+		assert!(!sessions.contains_key(&tokens[1]));
+	}
+
+	/// Buzzing while the buzzer is locked or the answer revealed is a false
+	/// start of the next question, replacing lockouts of this one.
+	#[tokio::test]
+	async fn false_starts_carry_over() {
+		let room = room().await;
+		let (alice, bob, carol) = (user("Alice"), user("Bob"), user("Carol"));
+		room.buzz(&alice, None, None).await;
+		room.buzz(&carol, None, None).await;
+		room.judge_answer(false).await;
+		room.judge_answer(false).await;
+		room.lock_round().await;
+
+		assert_eq!(room.buzz(&bob, None, None).await, None);
+		room.reveal_round().await;
+		assert_eq!(room.buzz(&alice, None, None).await, None);
+		assert_eq!(room.round().await.false_starts, [bob.id, alice.id]);
+
+		room.open_round(None).await;
+		let round = room.round().await;
+		assert_eq!(round.false_starts, [bob.id, alice.id]);
+		assert!(round.locked_out[&alice.id].is_some());
+		assert!(round.locked_out[&bob.id].is_some());
+		assert!(!round.locked_out.contains_key(&carol.id));
+	}
+}
@@ -0,0 +1,63 @@
+//! Frontend assets embedded into the executable
+
+use axum::{
+	http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
+	response::{IntoResponse, Response},
+	routing, Router,
+};
+
+/// Revalidate with the ETag on every use, as the file names of the frontend
+/// build do not change with their content.
+const CACHE_CONTROL: &str = "no-cache";
+
+/// Embedded frontend file
+struct Asset {
+	/// URL path of the file
+	path: &'static str,
+	/// Content of the file
+	content: &'static [u8],
+	/// ETag of the content, a quoted hash
+	etag: &'static str,
+}
+
+/// Frontend files, generated by the build script
+static ASSETS: &[Asset] = include!(concat!(env!("OUT_DIR"), "/frontend_assets.rs"));
+
+/// Frontend routes, serving the embedded files.
+pub fn routes() -> Router {
+	Router::new().fallback(routing::get(serve))
+}
+
+/// Serve the embedded file of the path. Other paths are routes of the frontend
+/// and get the index, except for missing files of the wasm build.
+async fn serve(uri: Uri, headers: HeaderMap) -> Response {
+	let path = uri.path();
+	let asset = match ASSETS.iter().find(|asset| asset.path == path) {
+		Some(asset) => asset,
+		None if path.starts_with("/pkg/") => return StatusCode::NOT_FOUND.into_response(),
+		None => match ASSETS.iter().find(|asset| asset.path == "/index.html") {
+			Some(index) => index,
+			None => return StatusCode::NOT_FOUND.into_response(),
+		},
+	};
+
+	let cache_headers = [(header::ETAG, asset.etag), (header::CACHE_CONTROL, CACHE_CONTROL)];
+	if is_cached(&headers, asset.etag) {
+		return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+	}
+	let content_type = mime_guess::from_path(asset.path).first_or_octet_stream();
+	let content_type = HeaderValue::from_str(content_type.as_ref())
+		.unwrap_or(HeaderValue::from_static("application/octet-stream"));
+	([(header::CONTENT_TYPE, content_type)], cache_headers, asset.content).into_response()
+}
+
+/// Check whether the client's `If-None-Match` matches the ETag.
+fn is_cached(headers: &HeaderMap, etag: &str) -> bool {
+	headers
+		.get_all(header::IF_NONE_MATCH)
+		.iter()
+		.filter_map(|value| value.to_str().ok())
+		.flat_map(|value| value.split(','))
+		.map(|tag| tag.trim().trim_start_matches("W/"))
+		.any(|tag| tag == etag || tag == "*")
+}
@@ -15,6 +15,7 @@ use jsonwebtoken::{
 };
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
+use uuid::Uuid;
 
 use crate::rooms::UserData;
 
@@ -59,6 +60,47 @@ impl Claims {
 	}
 }
 
+/// JWT Claims of room invites
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InviteClaims {
+	/// Issued at timestamp
+	#[serde(rename = "iat")]
+	pub issued_at: i64,
+	/// Expiry timestamp
+	#[serde(rename = "exp")]
+	pub expiry: i64,
+	/// ID of the room the invite is for
+	pub room_id: Uuid,
+}
+
+impl InviteClaims {
+	/// JWT encoding algorithm to use
+	const ALGORITHM: Algorithm = Algorithm::HS512;
+	/// Seconds until invites expire
+	const VALIDITY_SECONDS: u64 = 7 * 24 * 60 * 60; // 1 week
+
+	/// Create new invite claims for the room
+	pub fn new(room_id: Uuid) -> Self {
+		let now = OffsetDateTime::now_utc();
+		let issued_at = now.unix_timestamp();
+		let expiry = (now + Duration::from_secs(Self::VALIDITY_SECONDS)).unix_timestamp();
+		Self { issued_at, expiry, room_id }
+	}
+
+	/// Encode the claims to a JWT token
+	pub fn to_jwt(&self, keys: &JwtKeys) -> Result<String, Error> {
+		let header = Header::new(Self::ALGORITHM);
+		jsonwebtoken::encode(&header, self, &keys.encoding)
+	}
+
+	/// Decode the claims from a JWT token
+	pub fn from_jwt(jwt: &str, keys: &JwtKeys) -> Result<Self, Error> {
+		let validation = Validation::new(Self::ALGORITHM);
+		let token_data: TokenData<Self> = jsonwebtoken::decode(jwt, &keys.decoding, &validation)?;
+		Ok(token_data.claims)
+	}
+}
+
 /// Encoding and decoding keys
 #[derive(Clone)]
 pub struct JwtKeys {
@@ -95,6 +137,16 @@ impl JwtKeys {
 	pub fn decode_jwt(&self, jwt: &str) -> Result<Claims, Error> {
 		Claims::from_jwt(jwt, self)
 	}
+
+	/// Encode invite JWT using these keys
+	pub fn encode_invite(&self, claims: &InviteClaims) -> Result<String, Error> {
+		claims.to_jwt(self)
+	}
+
+	/// Decode invite JWT using these keys
+	pub fn decode_invite(&self, jwt: &str) -> Result<InviteClaims, Error> {
+		InviteClaims::from_jwt(jwt, self)
+	}
 }
 
 /// Authentication extractor using Authorization header and a Cookie as
@@ -126,7 +178,8 @@ impl<B: Send> FromRequest<B> for Authentication {
 			})?;
 
 		#[allow(clippy::expect_used)] // Fast failure, can't run at all
-		let keys = Extension::<JwtKeys>::from_request(req).await.expect("JWT keys extension must be set!");
+		let keys =
+			Extension::<JwtKeys>::from_request(req).await.expect("JWT keys extension must be set!");
 		let claims = keys.decode_jwt(token).map_err(|err| {
 			(StatusCode::UNAUTHORIZED, headers, format!("Error decoding JWT: {err}"))
 		})?;
@@ -1,7 +1,17 @@
 //! Server errors
 
-use axum::{http::StatusCode, response::IntoResponse};
+use std::time::Duration;
+
+use api_types::{
+	codec::CodecError,
+	websocket::{ErrorCode, ServerMessage},
+};
+use axum::{
+	http::{header, StatusCode},
+	response::IntoResponse,
+};
 use thiserror::Error;
+use tokio::task::JoinError;
 
 /// Server error type
 #[derive(Debug, Error)]
@@ -12,6 +22,25 @@ pub enum ServerError {
 	/// Invalid input error
 	#[error("Invalid input given: {0}")]
 	InvalidInput(String),
+	/// Room not found error
+	#[error("Room not found")]
+	RoomNotFound,
+	/// Access denied error
+	#[error("Access denied: {0}")]
+	Forbidden(String),
+	/// Event stream not found error
+	#[error("Event stream not found")]
+	StreamNotFound,
+	/// Server is shutting down error
+	#[error("Server is shutting down")]
+	ShuttingDown,
+	/// Too many requests error, with the time until the next request is
+	/// allowed
+	#[error("Too many requests")]
+	RateLimited(Duration),
+	/// Failed background task error
+	#[error("Background task failed: {0}")]
+	Task(#[from] JoinError),
 }
 
 impl IntoResponse for ServerError {
@@ -24,6 +53,87 @@ impl IntoResponse for ServerError {
 			Self::InvalidInput(err) => {
 				(StatusCode::BAD_REQUEST, format!("Invalid input given: {err}")).into_response()
 			}
+			Self::RoomNotFound => (StatusCode::NOT_FOUND, "Room not found").into_response(),
+			Self::StreamNotFound => {
+				(StatusCode::NOT_FOUND, "Event stream not found").into_response()
+			}
+			Self::ShuttingDown => {
+				(StatusCode::SERVICE_UNAVAILABLE, "Server is shutting down").into_response()
+			}
+			Self::RateLimited(retry_after) => {
+				// Round up, retrying too early would be limited again.
+				let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+				(
+					StatusCode::TOO_MANY_REQUESTS,
+					[(header::RETRY_AFTER, secs.to_string())],
+					"Too many requests, try again later",
+				)
+					.into_response()
+			}
+			Self::Forbidden(err) => {
+				(StatusCode::FORBIDDEN, format!("Access denied: {err}")).into_response()
+			}
+			Self::Task(err) => {
+				(StatusCode::INTERNAL_SERVER_ERROR, format!("Background task failed: {err}"))
+					.into_response()
+			}
+		}
+	}
+}
+
+/// Error of a websocket client, reported to the client with
+/// [`ServerMessage::Error`]
+#[derive(Debug, Error)]
+pub enum ClientError {
+	/// Missing handshake or unsupported protocol version
+	#[error("{0}")]
+	IncompatibleProtocol(String),
+	/// Message could not be parsed
+	#[error("Malformed message: {0}")]
+	MalformedMessage(#[from] CodecError),
+	/// Host-only action by another member
+	#[error("Only the host can {0}")]
+	HostOnly(&'static str),
+	/// Action of a spectator
+	#[error("Spectators can only watch")]
+	Spectator,
+	/// Empty or taken name
+	#[error("{0}")]
+	InvalidName(&'static str),
+	/// Action that does not fit the room
+	#[error("{0}")]
+	InvalidAction(&'static str),
+	/// Message over the rate limit, which was dropped
+	#[error("Too many messages, slow down")]
+	RateLimited,
+	/// Client kept sending messages while rate limited
+	#[error("Too many messages, disconnecting")]
+	Flooding,
+}
+
+impl ClientError {
+	/// Error code of the error.
+	#[must_use]
+	pub const fn code(&self) -> ErrorCode {
+		match self {
+			Self::IncompatibleProtocol(_) => ErrorCode::IncompatibleProtocol,
+			Self::MalformedMessage(_) => ErrorCode::MalformedMessage,
+			Self::HostOnly(_) | Self::Spectator => ErrorCode::Forbidden,
+			Self::InvalidName(_) => ErrorCode::InvalidName,
+			Self::InvalidAction(_) => ErrorCode::InvalidAction,
+			Self::RateLimited | Self::Flooding => ErrorCode::RateLimited,
 		}
 	}
+
+	/// Whether the error closes the connection.
+	#[must_use]
+	pub const fn is_fatal(&self) -> bool {
+		matches!(self, Self::IncompatibleProtocol(_) | Self::MalformedMessage(_) | Self::Flooding)
+	}
+
+	/// Message reporting the error to the client.
+	#[must_use]
+	pub fn to_message(&self) -> ServerMessage {
+		ServerMessage::Error { code: self.code(), message: self.to_string() }
+	}
 }
@@ -0,0 +1,183 @@
+//! Prometheus metrics
+
+use std::{sync::LazyLock, time::Instant};
+
+use api_types::websocket::ClientMessage;
+use axum::{
+	extract::MatchedPath,
+	http::{header, Request, StatusCode},
+	middleware::Next,
+	response::IntoResponse,
+	Extension,
+};
+use prometheus::{
+	core::Collector, exponential_buckets, Encoder, Histogram, HistogramOpts, HistogramVec,
+	IntCounter, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+use super::RoomDb;
+
+/// Buckets of the room size histogram
+const ROOM_SIZE_BUCKETS: [f64; 9] = [1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0];
+
+/// Metrics of the server
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// Metrics of the server, registered in their own registry
+pub struct Metrics {
+	/// Registry of the metrics
+	registry: Registry,
+	/// Number of rooms, updated when gathering
+	rooms: IntGauge,
+	/// Number of members in all rooms, updated when gathering
+	members: IntGauge,
+	/// Number of open connections by transport
+	pub connections: IntGaugeVec,
+	/// Number of accepted buzzes
+	pub buzzes: IntCounter,
+	/// Number of times a connection lagged behind the room's events
+	pub broadcast_lags: IntCounter,
+	/// Number of logins
+	pub logins: IntCounter,
+	/// Duration of HTTP requests by method, route and status
+	pub request_duration: HistogramVec,
+	/// Duration of handling client messages by message type
+	pub message_duration: HistogramVec,
+}
+
+impl Metrics {
+	/// Create and register the metrics.
+	#[allow(clippy::expect_used)] // metric names and labels are constant
+	fn new() -> Self {
+		let metrics = Self {
+			registry: Registry::new(),
+			rooms: IntGauge::new("buzzer_rooms", "Number of rooms").expect("valid metric"),
+			members: IntGauge::new("buzzer_members", "Number of members in all rooms")
+				.expect("valid metric"),
+			connections: IntGaugeVec::new(
+				Opts::new("buzzer_connections", "Number of open connections by transport"),
+				&["transport"],
+			)
+			.expect("valid metric"),
+			buzzes: IntCounter::new("buzzer_buzzes_total", "Number of accepted buzzes")
+				.expect("valid metric"),
+			broadcast_lags: IntCounter::new(
+				"buzzer_broadcast_lags_total",
+				"Number of times a connection lagged behind the room's events and was resynced",
+			)
+			.expect("valid metric"),
+			logins: IntCounter::new("buzzer_logins_total", "Number of logins")
+				.expect("valid metric"),
+			request_duration: HistogramVec::new(
+				HistogramOpts::new(
+					"buzzer_http_request_duration_seconds",
+					"Duration of HTTP requests until the response",
+				),
+				&["method", "route", "status"],
+			)
+			.expect("valid metric"),
+			message_duration: HistogramVec::new(
+				HistogramOpts::new(
+					"buzzer_message_duration_seconds",
+					"Duration of handling client messages by message type",
+				)
+				.buckets(exponential_buckets(0.000_1, 4.0, 8).expect("valid buckets")),
+				&["type"],
+			)
+			.expect("valid metric"),
+		};
+
+		let registry = &metrics.registry;
+		registry.register(Box::new(metrics.rooms.clone())).expect("unique metric");
+		registry.register(Box::new(metrics.members.clone())).expect("unique metric");
+		registry.register(Box::new(metrics.connections.clone())).expect("unique metric");
+		registry.register(Box::new(metrics.buzzes.clone())).expect("unique metric");
+		registry.register(Box::new(metrics.broadcast_lags.clone())).expect("unique metric");
+		registry.register(Box::new(metrics.logins.clone())).expect("unique metric");
+		registry.register(Box::new(metrics.request_duration.clone())).expect("unique metric");
+		registry.register(Box::new(metrics.message_duration.clone())).expect("unique metric");
+		metrics
+	}
+
+	/// Update the room metrics and render all metrics in the Prometheus text
+	/// format.
+	async fn render(&self, room_db: &RoomDb) -> Result<String, prometheus::Error> {
+		let rooms = room_db.rooms().await;
+		self.rooms.set(rooms.len().try_into().unwrap_or(i64::MAX));
+		// Room IDs grant access to unprotected rooms, so only totals and the
+		// distribution of sizes are reported. The distribution is a snapshot,
+		// so it is built anew every time.
+		let room_sizes = Histogram::with_opts(
+			HistogramOpts::new("buzzer_room_members", "Number of members per room")
+				.buckets(ROOM_SIZE_BUCKETS.to_vec()),
+		)?;
+		let mut members = 0_usize;
+		for (_id, room) in &rooms {
+			let size = room.num_members().await;
+			members = members.saturating_add(size);
+			room_sizes.observe(size as f64);
+		}
+		self.members.set(members.try_into().unwrap_or(i64::MAX));
+
+		let mut families = self.registry.gather();
+		families.extend(room_sizes.collect());
+		let mut buffer = Vec::new();
+		TextEncoder::new().encode(&families, &mut buffer)?;
+		Ok(String::from_utf8_lossy(&buffer).into_owned())
+	}
+}
+
+/// Handler for the Prometheus metrics.
+pub async fn metrics(Extension(room_db): Extension<RoomDb>) -> impl IntoResponse {
+	match METRICS.render(&room_db).await {
+		Ok(metrics) => Ok(([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], metrics)),
+		Err(err) => {
+			tracing::error!("Could not render metrics: {err}");
+			Err(StatusCode::INTERNAL_SERVER_ERROR)
+		}
+	}
+}
+
+/// Get the label of the client message's type.
+pub fn message_type(msg: &ClientMessage) -> &'static str {
+	match msg {
+		ClientMessage::Hello(_) => "hello",
+		ClientMessage::Buzz { .. } => "buzz",
+		ClientMessage::FreeBuzzer => "free_buzzer",
+		ClientMessage::OpenRound { .. } => "open_round",
+		ClientMessage::LockRound => "lock_round",
+		ClientMessage::RevealRound => "reveal_round",
+		ClientMessage::ResetRound => "reset_round",
+		ClientMessage::NextBuzzer => "next_buzzer",
+		ClientMessage::AwardPoints { .. } => "award_points",
+		ClientMessage::CreateTeam(_) => "create_team",
+		ClientMessage::RemoveTeam(_) => "remove_team",
+		ClientMessage::JoinTeam(_) => "join_team",
+		ClientMessage::AssignTeam { .. } => "assign_team",
+		ClientMessage::TransferHost(_) => "transfer_host",
+		ClientMessage::Kick { .. } => "kick",
+		ClientMessage::Ban { .. } => "ban",
+		ClientMessage::JudgeAnswer { .. } => "judge_answer",
+		ClientMessage::ChangeName(_) => "change_name",
+		ClientMessage::Resync => "resync",
+		ClientMessage::Leave => "leave",
+		ClientMessage::Pong { .. } => "pong",
+	}
+}
+
+/// Middleware measuring the duration of requests by matched route.
+pub async fn track_requests<B>(req: Request<B>, next: Next<B>) -> impl IntoResponse {
+	let route = req
+		.extensions()
+		.get::<MatchedPath>()
+		.map_or_else(String::new, |path| path.as_str().to_owned());
+	let method = req.method().to_string();
+	let start = Instant::now();
+
+	let response = next.run(req).await;
+	METRICS
+		.request_duration
+		.with_label_values(&[&method, &route, response.status().as_str()])
+		.observe(start.elapsed().as_secs_f64());
+	response
+}
@@ -1,32 +1,131 @@
 //! Webserver functionality
 
+#[cfg(feature = "embed-frontend")]
+mod assets;
 mod auth;
 mod error;
+mod metrics;
+mod rate_limit;
+mod room_db;
 mod routes;
+mod shutdown;
+mod socket;
 
-use std::{collections::BTreeMap, sync::Arc};
+use std::{net::SocketAddr, time::Duration};
 
 use auth::JwtKeys;
-use axum::{http::StatusCode, routing, Extension, Router};
-use tokio::sync::Mutex;
+#[cfg(not(feature = "embed-frontend"))]
+use axum::http::StatusCode;
+use axum::{middleware, routing, Extension, Router};
+use rate_limit::IpRateLimiter;
+use room_db::RoomDb;
+use shutdown::Shutdown;
+use socket::EventStreams;
+#[cfg(not(feature = "embed-frontend"))]
 use tower_http::services::{ServeDir, ServeFile};
-use uuid::Uuid;
 
-use crate::{rooms::RoomState, settings::Settings};
+use crate::{
+	settings::Settings,
+	storage::{Storage, StorageError},
+};
 
-/// Type of room cache, saving room data.
-type RoomDb = Arc<Mutex<BTreeMap<Uuid, Arc<RoomState>>>>;
+/// Webserver routes
+#[derive(Debug)]
+pub struct Routes {
+	/// Routes of the app
+	pub app: Router,
+	/// Metrics routes with their bind address, if they are served separately
+	/// from the app
+	pub metrics: Option<(SocketAddr, Router)>,
+	/// Graceful shutdown of the rooms
+	pub shutdown: Shutdown,
+}
 
 /// Webserver routes
-pub fn routes(settings: Settings) -> Router {
-	let room_db = RoomDb::default();
+pub async fn routes(settings: Settings) -> Result<Routes, StorageError> {
+	let storage = match settings.database_url.as_deref() {
+		Some(url) => Some(Storage::connect(url).await?),
+		None => None,
+	};
+	let room_expiry = Duration::from_secs(settings.room_expiry_secs);
+	let reconnect_grace = Duration::from_secs(settings.reconnect_grace_secs);
+	let rate_limits = settings.rate_limits;
+	let room_db =
+		RoomDb::load(settings.rooms, storage, room_expiry, reconnect_grace, rate_limits.messages)
+			.await?;
 	let jwt_keys = JwtKeys::from_secret(&settings.jwt_secret);
+	let drain_timeout = Duration::from_secs(settings.shutdown_drain_secs);
+	let shutdown = Shutdown::new(room_db.clone(), drain_timeout);
+	let login_limiter = IpRateLimiter::new(rate_limits.login);
+	let limit_logins = middleware::from_fn(move |req, next| login_limiter.clone().limit(req, next));
+	let connection_limiter = IpRateLimiter::new(rate_limits.connections);
+	let limit_connections =
+		middleware::from_fn(move |req, next| connection_limiter.clone().limit(req, next));
 
-	Router::new()
+	let mut app = Router::new()
 		// Backend routes
-		.route("/login", routing::post(routes::login))
-		.route("/:room_id/ws", routing::get(routes::websocket_upgrade))
+		.route("/login", routing::post(routes::login).route_layer(limit_logins.clone()))
+		.route(
+			"/rooms",
+			routing::post(routes::create_room)
+				.route_layer(limit_connections.clone())
+				.get(routes::list_rooms),
+		)
+		// Checking room passwords is limited like logins against guessing.
+		.route(
+			"/rooms/:room_id",
+			routing::get(routes::room_state)
+				.route_layer(limit_logins.clone())
+				.delete(routes::close_room),
+		)
+		.route(
+			"/rooms/:room_id/access",
+			routing::get(routes::room_access).route_layer(limit_logins),
+		)
+		.route("/rooms/:room_id/invite", routing::get(routes::room_invite))
+		.route(
+			"/:room_id/ws",
+			routing::get(routes::websocket_upgrade).route_layer(limit_connections.clone()),
+		)
+		.route(
+			"/:room_id/events",
+			routing::get(routes::event_stream).route_layer(limit_connections),
+		)
+		.route("/:room_id/messages", routing::post(routes::post_message))
+		.route_layer(middleware::from_fn(metrics::track_requests))
+		// Health routes
+		.route("/healthz", routing::get(routes::healthz))
+		.route("/readyz", routing::get(routes::readyz))
 		// Frontend routes
+		.merge(frontend_routes())
+		// Layers
+		.layer(Extension(jwt_keys))
+		.layer(Extension(room_db.clone()))
+		.layer(Extension(EventStreams::default()));
+
+	let mut metrics = None;
+	if let Some(settings) = settings.metrics {
+		let router = Router::new()
+			.route(&settings.path, routing::get(metrics::metrics))
+			.layer(Extension(room_db.clone()));
+		match settings.bind {
+			Some(bind) => metrics = Some((bind, router)),
+			None => app = app.merge(router),
+		}
+	}
+	Ok(Routes { app, metrics, shutdown })
+}
+
+/// Frontend routes, serving the files embedded into the executable.
+#[cfg(feature = "embed-frontend")]
+fn frontend_routes() -> Router {
+	assets::routes()
+}
+
+/// Frontend routes, serving the files from the `frontend` directory.
+#[cfg(not(feature = "embed-frontend"))]
+fn frontend_routes() -> Router {
+	Router::new()
 		.route(
 			"/index.js",
 			routing::get_service(ServeFile::new("frontend/index.js")).handle_error(handle_error),
@@ -42,12 +141,10 @@ pub fn routes(settings: Settings) -> Router {
 		.fallback(
 			routing::get_service(ServeFile::new("frontend/index.html")).handle_error(handle_error),
 		)
-		// Layers
-		.layer(Extension(jwt_keys))
-		.layer(Extension(room_db))
 }
 
 /// Handle errors when serving files.
+#[cfg(not(feature = "embed-frontend"))]
 #[allow(clippy::unused_async)] // Is axum handler.
 async fn handle_error(err: std::io::Error) -> (StatusCode, String) {
 	(StatusCode::INTERNAL_SERVER_ERROR, format!("Could not serve file: {}", err))
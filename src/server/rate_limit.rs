@@ -0,0 +1,233 @@
+//! Token-bucket rate limiting of clients
+
+use std::{
+	collections::HashMap,
+	net::{IpAddr, SocketAddr},
+	sync::{Arc, Mutex, PoisonError},
+	time::{Duration, Instant},
+};
+
+use axum::{
+	extract::ConnectInfo,
+	http::Request,
+	middleware::Next,
+	response::{IntoResponse, Response},
+};
+
+use super::error::{ClientError, ServerError};
+use crate::settings::RateLimit;
+
+/// Interval of removing the buckets of clients that are not limited anymore.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Token bucket, allowing bursts up to its capacity and refilling at a
+/// constant rate
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+	/// Capacity and refill rate
+	limit: RateLimit,
+	/// Available tokens
+	tokens: f64,
+	/// Time the tokens were last refilled
+	refilled_at: Instant,
+}
+
+impl TokenBucket {
+	/// Create a full bucket.
+	#[must_use]
+	pub fn new(limit: RateLimit) -> Self {
+		Self { limit, tokens: limit.burst.into(), refilled_at: Instant::now() }
+	}
+
+	/// Refill the tokens for the time passed since the last refill.
+	fn refill(&mut self, now: Instant) {
+		let passed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+		self.tokens =
+			passed.mul_add(self.limit.per_second, self.tokens).min(self.limit.burst.into());
+		self.refilled_at = now;
+	}
+
+	/// Whether the bucket is full again after refilling.
+	fn is_full(&mut self, now: Instant) -> bool {
+		self.refill(now);
+		self.tokens >= self.limit.burst.into()
+	}
+
+	/// Take a token. Returns the time until the next token is available if the
+	/// bucket is empty.
+	pub fn take(&mut self) -> Result<(), Duration> {
+		self.refill(Instant::now());
+		if self.tokens >= 1.0 {
+			self.tokens -= 1.0;
+			Ok(())
+		} else {
+			let wait = (1.0 - self.tokens) / self.limit.per_second;
+			Err(Duration::try_from_secs_f64(wait).unwrap_or(Duration::MAX))
+		}
+	}
+}
+
+/// Buckets of an [`IpRateLimiter`]
+#[derive(Debug)]
+struct IpBuckets {
+	/// Token bucket by client IP address
+	buckets: HashMap<IpAddr, TokenBucket>,
+	/// Time the full buckets were last removed
+	pruned_at: Instant,
+}
+
+/// Rate limiter of requests by client IP address. Cheap to clone.
+#[derive(Debug, Clone)]
+pub struct IpRateLimiter {
+	/// Rate limit of each IP address
+	limit: RateLimit,
+	/// Buckets of the IP addresses
+	buckets: Arc<Mutex<IpBuckets>>,
+}
+
+impl IpRateLimiter {
+	/// Create the rate limiter with the limit of each IP address.
+	#[must_use]
+	pub fn new(limit: RateLimit) -> Self {
+		let buckets = IpBuckets { buckets: HashMap::new(), pruned_at: Instant::now() };
+		Self { limit, buckets: Arc::new(Mutex::new(buckets)) }
+	}
+
+	/// Take a token of the IP address. Returns the time until the next token
+	/// is available if the address is over the limit.
+	pub fn take(&self, ip: IpAddr) -> Result<(), Duration> {
+		let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+		let now = Instant::now();
+		if now.saturating_duration_since(buckets.pruned_at) >= PRUNE_INTERVAL {
+			// Full buckets are the same as new ones.
+			buckets.buckets.retain(|_ip, bucket| !bucket.is_full(now));
+			buckets.pruned_at = now;
+		}
+		buckets.buckets.entry(ip).or_insert_with(|| TokenBucket::new(self.limit)).take()
+	}
+
+	/// Middleware rejecting requests of IP addresses over the limit. Requests
+	/// without connection info are not limited.
+	pub async fn limit<B>(self, req: Request<B>, next: Next<B>) -> Response {
+		if let Some(ConnectInfo(addr)) = req.extensions().get::<ConnectInfo<SocketAddr>>() {
+			if let Err(retry_after) = self.take(addr.ip()) {
+				tracing::debug!("Rate limited requests of {}.", addr.ip());
+				return ServerError::RateLimited(retry_after).into_response();
+			}
+		}
+		next.run(req).await
+	}
+}
+
+/// Rate limiter of a connection's messages
+#[derive(Debug)]
+pub struct MessageLimiter {
+	/// Bucket of the connection
+	bucket: TokenBucket,
+	/// Number of messages dropped in a row
+	dropped: u32,
+}
+
+impl MessageLimiter {
+	/// Create the rate limiter of a connection.
+	#[must_use]
+	pub fn new(limit: RateLimit) -> Self {
+		Self { bucket: TokenBucket::new(limit), dropped: 0 }
+	}
+
+	/// Check whether the next message is allowed. Messages over the limit are
+	/// dropped, and clients that keep sending another burst of messages while
+	/// limited are disconnected.
+	pub fn check(&mut self) -> Result<(), ClientError> {
+		if self.bucket.take().is_ok() {
+			self.dropped = 0;
+			return Ok(());
+		}
+
+		self.dropped = self.dropped.saturating_add(1);
+		if self.dropped > self.bucket.limit.burst {
+			Err(ClientError::Flooding)
+		} else {
+			Err(ClientError::RateLimited)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::{MessageLimiter, TokenBucket};
+	use crate::{server::error::ClientError, settings::RateLimit};
+
+	/// Limit of two messages, refilling one token per second
+	const LIMIT: RateLimit = RateLimit { burst: 2, per_second: 1.0 };
+
+	/// Let the time pass for the bucket.
+	fn pass(bucket: &mut TokenBucket, time: Duration) {
+		let later = bucket.refilled_at + time;
+		bucket.refill(later);
+	}
+
+	/// A bucket allows bursts and then reports the time until the next token.
+	#[test]
+	fn token_bucket_take() {
+		let mut bucket = TokenBucket::new(LIMIT);
+		assert!(bucket.take().is_ok());
+		assert!(bucket.take().is_ok());
+
+		assert!(matches!(
+			bucket.take(),
+			Err(wait) if wait > Duration::from_millis(900) && wait <= Duration::from_secs(1)
+		));
+
+		pass(&mut bucket, Duration::from_millis(500));
+		assert!(matches!(
+			bucket.take(),
+			Err(wait) if wait > Duration::from_millis(400) && wait <= Duration::from_millis(500)
+		));
+
+		pass(&mut bucket, Duration::from_millis(500));
+		assert!(bucket.take().is_ok());
+		assert!(bucket.take().is_err());
+	}
+
+	/// A bucket refills up to its burst only.
+	#[test]
+	fn token_bucket_refill_is_capped() {
+		let mut bucket = TokenBucket::new(LIMIT);
+		assert!(bucket.take().is_ok());
+		pass(&mut bucket, Duration::from_secs(60));
+		assert!(bucket.take().is_ok());
+		assert!(bucket.take().is_ok());
+		assert!(bucket.take().is_err());
+	}
+
+	/// Messages over the limit are dropped, and another burst of dropped
+	/// messages is flooding.
+	#[test]
+	fn message_limiter_check() {
+		let mut limiter = MessageLimiter::new(LIMIT);
+		assert!(limiter.check().is_ok());
+		assert!(limiter.check().is_ok());
+		assert!(matches!(limiter.check(), Err(ClientError::RateLimited)));
+		assert!(matches!(limiter.check(), Err(ClientError::RateLimited)));
+		assert!(matches!(limiter.check(), Err(ClientError::Flooding)));
+	}
+
+	/// An allowed message resets the count of dropped messages.
+	#[test]
+	fn message_limiter_resets_after_allowed_message() {
+		let mut limiter = MessageLimiter::new(LIMIT);
+		assert!(limiter.check().is_ok());
+		assert!(limiter.check().is_ok());
+		assert!(matches!(limiter.check(), Err(ClientError::RateLimited)));
+		assert!(matches!(limiter.check(), Err(ClientError::RateLimited)));
+
+		pass(&mut limiter.bucket, Duration::from_secs(1));
+		assert!(limiter.check().is_ok());
+		assert!(matches!(limiter.check(), Err(ClientError::RateLimited)));
+		assert!(matches!(limiter.check(), Err(ClientError::RateLimited)));
+		assert!(matches!(limiter.check(), Err(ClientError::Flooding)));
+	}
+}
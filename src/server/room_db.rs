@@ -0,0 +1,287 @@
+//! Room database, keeping rooms in memory and optionally persisting them.
+
+use std::{
+	collections::BTreeMap,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, Weak,
+	},
+	time::Duration,
+};
+
+use api_types::websocket::{ServerEvent, ServerMessage};
+use tokio::sync::{broadcast::error::RecvError, watch, Mutex};
+use uuid::Uuid;
+
+use crate::{
+	rooms::{now_millis, RoomAccess, RoomState},
+	settings::{RateLimit, RoomSettings},
+	storage::{Storage, StorageError},
+};
+
+/// Interval of checking for expired rooms.
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Room database, saving room data. Cheap to clone.
+#[derive(Debug, Clone)]
+pub struct RoomDb {
+	/// Rooms currently loaded in memory
+	rooms: Arc<Mutex<BTreeMap<Uuid, Arc<RoomState>>>>,
+	/// Persistent storage backend, if configured
+	storage: Option<Storage>,
+	/// Configuration for new rooms
+	room_settings: RoomSettings,
+	/// Time that disconnected members keep their seat to reconnect
+	reconnect_grace: Duration,
+	/// Rate limit of each connection's messages
+	message_limit: RateLimit,
+	/// Whether the server is shutting down and does not accept new rooms and
+	/// connections anymore
+	shutting_down: Arc<AtomicBool>,
+	/// Closed when all tracked connections ended
+	connections: Arc<watch::Sender<()>>,
+}
+
+impl RoomDb {
+	/// Create the room database and load all rooms from the storage if
+	/// available. Spawns the background task deleting rooms that have been
+	/// empty for longer than the room expiry.
+	pub async fn load(
+		room_settings: RoomSettings,
+		storage: Option<Storage>,
+		room_expiry: Duration,
+		reconnect_grace: Duration,
+		message_limit: RateLimit,
+	) -> Result<Self, StorageError> {
+		let room_db = Self {
+			rooms: Arc::default(),
+			storage,
+			room_settings,
+			reconnect_grace,
+			message_limit,
+			shutting_down: Arc::default(),
+			connections: Arc::new(watch::channel(()).0),
+		};
+
+		if let Some(storage) = room_db.storage.as_ref() {
+			let snapshots = storage.load_rooms().await?;
+			tracing::info!("Loaded {} rooms from the database.", snapshots.len());
+
+			let mut rooms = room_db.rooms.lock().await;
+			for (id, snapshot) in snapshots {
+				let room = Arc::new(RoomState::from_snapshot(snapshot));
+				RoomState::spawn_round_timer(&room);
+				room_db.spawn_persistence(id, &room);
+				room_db.spawn_member_expiry(&room).await;
+				rooms.insert(id, room);
+			}
+		}
+
+		tokio::spawn(room_db.clone().cleanup(room_expiry));
+		Ok(room_db)
+	}
+
+	/// Get the room with the given ID, if it exists.
+	pub async fn get(&self, id: Uuid) -> Option<Arc<RoomState>> {
+		self.rooms.lock().await.get(&id).cloned()
+	}
+
+	/// Get the time that disconnected members keep their seat to reconnect.
+	#[must_use]
+	pub const fn reconnect_grace(&self) -> Duration {
+		self.reconnect_grace
+	}
+
+	/// Get the rate limit of each connection's messages.
+	#[must_use]
+	pub const fn message_limit(&self) -> RateLimit {
+		self.message_limit
+	}
+
+	/// Get whether the server is shutting down. No new rooms and connections
+	/// are accepted meanwhile.
+	#[must_use]
+	pub fn is_shutting_down(&self) -> bool {
+		self.shutting_down.load(Ordering::SeqCst)
+	}
+
+	/// Check whether the storage is reachable, if configured.
+	pub async fn is_healthy(&self) -> bool {
+		match self.storage.as_ref() {
+			Some(storage) => storage.ping().await.is_ok(),
+			None => true,
+		}
+	}
+
+	/// Track an open connection until the returned guard is dropped, so that
+	/// shutting down waits for it.
+	#[must_use]
+	pub fn track_connection(&self) -> watch::Receiver<()> {
+		self.connections.subscribe()
+	}
+
+	/// Stop accepting new rooms and connections, notify all connections that
+	/// the server is restarting and wait for them to close, at most for the
+	/// drain timeout. Saves all rooms to the storage afterwards.
+	pub async fn shut_down(&self, drain_timeout: Duration) {
+		self.shutting_down.store(true, Ordering::SeqCst);
+		for room in self.rooms.lock().await.values() {
+			room.restart();
+		}
+
+		let drained = tokio::time::timeout(drain_timeout, self.connections.closed()).await;
+		if drained.is_err() {
+			let open = self.connections.receiver_count();
+			tracing::warn!("{open} connections did not close within the drain timeout.");
+		}
+
+		if let Some(storage) = self.storage.as_ref() {
+			for (id, room) in self.rooms().await {
+				if let Err(err) = storage.save_room(id, &room.snapshot().await).await {
+					tracing::error!("Could not save room {id}: {err}");
+				}
+			}
+		}
+	}
+
+	/// Get all rooms.
+	pub async fn rooms(&self) -> Vec<(Uuid, Arc<RoomState>)> {
+		self.rooms.lock().await.iter().map(|(id, room)| (*id, room.clone())).collect()
+	}
+
+	/// Get all rooms that are listed publicly.
+	pub async fn public_rooms(&self) -> Vec<(Uuid, Arc<RoomState>)> {
+		self.rooms
+			.lock()
+			.await
+			.iter()
+			.filter(|(_, room)| room.access().public)
+			.map(|(id, room)| (*id, room.clone()))
+			.collect()
+	}
+
+	/// Create a new room with the given configuration and access restrictions.
+	/// Uses the default configuration if none is given. Returns the new room's
+	/// ID.
+	pub async fn create(
+		&self,
+		settings: Option<RoomSettings>,
+		access: RoomAccess,
+	) -> (Uuid, Arc<RoomState>) {
+		let mut rooms = self.rooms.lock().await;
+		let mut id = Uuid::new_v4();
+		while rooms.contains_key(&id) {
+			id = Uuid::new_v4();
+		}
+
+		let settings = settings.unwrap_or_else(|| self.room_settings.clone());
+		let room = Arc::new(RoomState::new(settings, access));
+		RoomState::spawn_round_timer(&room);
+		self.spawn_persistence(id, &room);
+		rooms.insert(id, room.clone());
+		drop(rooms);
+
+		// Save immediately, as there are no events until somebody joins.
+		if let Some(storage) = self.storage.as_ref() {
+			if let Err(err) = storage.save_room(id, &room.snapshot().await).await {
+				tracing::error!("Could not save room {id}: {err}");
+			}
+		}
+		(id, room)
+	}
+
+	/// Close the room, disconnecting all members, and delete it. Returns the
+	/// closed room if it existed.
+	pub async fn close(&self, id: Uuid) -> Option<Arc<RoomState>> {
+		let room = self.rooms.lock().await.remove(&id)?;
+		room.close();
+
+		if let Some(storage) = self.storage.as_ref() {
+			if let Err(err) = storage.delete_room(id).await {
+				tracing::error!("Could not delete room {id}: {err}");
+			}
+		}
+		Some(room)
+	}
+
+	/// Save the room to the storage whenever it publishes an event, until it is
+	/// closed or dropped.
+	fn spawn_persistence(&self, id: Uuid, room: &Arc<RoomState>) {
+		let storage = match self.storage.clone() {
+			Some(storage) => storage,
+			None => return,
+		};
+		let mut events = room.subscribe();
+		let room = Arc::downgrade(room);
+
+		tokio::spawn(async move {
+			loop {
+				match events.recv().await {
+					// Ticks do not change the persistent state.
+					Ok(ServerEvent { message: ServerMessage::Tick(_), .. }) => continue,
+					Ok(_) | Err(RecvError::Lagged(_)) => {}
+					Err(RecvError::Closed) => break,
+				}
+				let snapshot = match Weak::upgrade(&room) {
+					Some(room) if !room.is_closed() => room.snapshot().await,
+					_ => break,
+				};
+				if let Err(err) = storage.save_room(id, &snapshot).await {
+					tracing::error!("Could not save room {id}: {err}");
+				}
+			}
+		});
+	}
+
+	/// Remove the restored members of the room that do not reconnect within
+	/// the reconnection grace period.
+	async fn spawn_member_expiry(&self, room: &Arc<RoomState>) {
+		let disconnected = room.disconnected_members().await;
+		if disconnected.is_empty() {
+			return;
+		}
+		let grace = self.reconnect_grace;
+		let room = Arc::downgrade(room);
+
+		tokio::spawn(async move {
+			tokio::time::sleep(grace).await;
+			if let Some(room) = Weak::upgrade(&room) {
+				for (id, disconnected_at) in disconnected {
+					room.expire_member(id, disconnected_at).await;
+				}
+			}
+		});
+	}
+
+	/// Periodically delete rooms that have been empty for longer than the
+	/// room expiry.
+	async fn cleanup(self, room_expiry: Duration) {
+		let period = CLEANUP_INTERVAL.min(room_expiry).max(Duration::from_secs(1));
+		let mut interval = tokio::time::interval(period);
+		loop {
+			interval.tick().await;
+
+			let expired_before = now_millis() - room_expiry.as_millis() as f64;
+			let mut expired = Vec::new();
+			let mut rooms = self.rooms.lock().await;
+			for (id, room) in rooms.iter() {
+				if room.is_empty().await && room.last_active().await < expired_before {
+					expired.push(*id);
+				}
+			}
+			for id in &expired {
+				rooms.remove(id);
+			}
+			drop(rooms);
+
+			for id in expired {
+				tracing::debug!("Deleting expired room {id}.");
+				if let Some(storage) = self.storage.as_ref() {
+					if let Err(err) = storage.delete_room(id).await {
+						tracing::error!("Could not delete room {id}: {err}");
+					}
+				}
+			}
+		}
+	}
+}
@@ -1,95 +1,523 @@
 //! Webserver handlers
 #![allow(clippy::unused_async)]
 
+use std::{convert::Infallible, sync::Arc, time::Duration};
+
 use api_types::{
-	websocket::{ClientMessage, ServerMessage},
-	LoginInfo, LoginResponse,
+	codec::Codec,
+	websocket::{self, Capability, ClientMessage, Hello, ServerEvent, ServerMessage},
+	CreateRoom, CreateRoomResponse, JoinQuery, LoginInfo, LoginResponse, PublicRoom, Role,
+	RoomAccessInfo, StreamQuery,
 };
 use axum::{
-	extract::{
-		ws::{Message, WebSocket},
-		Path, WebSocketUpgrade,
+	extract::{Path, Query, WebSocketUpgrade},
+	http::{header, HeaderMap, StatusCode},
+	response::{
+		sse::{KeepAlive, Sse},
+		IntoResponse,
 	},
-	response::IntoResponse,
 	Extension, Json,
 };
 use axum_macros::debug_handler;
+use tokio::{sync::broadcast::error::RecvError, task};
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
 use uuid::Uuid;
 
 use super::{
-	auth::{Authentication, Claims, JwtKeys},
-	error::ServerError,
+	auth::{Authentication, Claims, InviteClaims, JwtKeys},
+	error::{ClientError, ServerError},
+	metrics::{self, METRICS},
+	rate_limit::MessageLimiter,
+	socket::{EventStreams, Socket},
 	RoomDb,
 };
-use crate::rooms::{RoomState, UserData};
+use crate::{
+	password::PasswordHash,
+	rooms::{now_millis, Latency, RoomAccess, RoomState, UserData},
+};
+
+/// Interval of latency measurements on websocket connections.
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+/// Time for clients to send their hello after connecting.
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Handler for "login", i.e. setting user data and receiving a token for
-/// authentication.
+/// authentication. Logging in with a valid token keeps the user ID, so only the
+/// name changes.
 #[debug_handler]
 pub async fn login(
 	jwt_keys: Extension<JwtKeys>,
+	claims: Option<Authentication>,
 	Json(login_info): Json<LoginInfo>,
 ) -> Result<Json<LoginResponse>, ServerError> {
 	if !login_info.is_valid() {
 		return Err(ServerError::InvalidInput("Invalid user data!".to_owned()));
 	}
 
-	let user_data = UserData::from(login_info);
+	let mut user_data = UserData::from(login_info);
+	if let Some(claims) = claims {
+		user_data.id = claims.0.user_data.id;
+	}
+	let id = user_data.id;
 	let claims = Claims::new(user_data);
 	let token = jwt_keys.encode_jwt(&claims)?;
-	Ok(Json(LoginResponse { token }))
+	METRICS.logins.inc();
+	Ok(Json(LoginResponse { token, id }))
+}
+
+/// Handler for the liveness probe.
+#[debug_handler]
+pub async fn healthz() -> StatusCode {
+	StatusCode::OK
+}
+
+/// Handler for the readiness probe. The server is not ready while shutting
+/// down or if the database is unreachable.
+#[debug_handler]
+pub async fn readyz(Extension(room_db): Extension<RoomDb>) -> StatusCode {
+	if room_db.is_shutting_down() || !room_db.is_healthy().await {
+		StatusCode::SERVICE_UNAVAILABLE
+	} else {
+		StatusCode::OK
+	}
+}
+
+/// Handler for listing the public rooms.
+#[debug_handler]
+pub async fn list_rooms(
+	Extension(room_db): Extension<RoomDb>,
+	_claims: Authentication,
+) -> Json<Vec<PublicRoom>> {
+	let mut rooms = Vec::new();
+	for (id, room) in room_db.public_rooms().await {
+		let access = room.access();
+		rooms.push(PublicRoom {
+			id,
+			host: room.host_name().await,
+			members: room.num_members().await,
+			password: access.password_hash.is_some(),
+			invite_only: access.invite_only,
+		});
+	}
+	Json(rooms)
+}
+
+/// Handler for creating a new room with the given configuration, optionally
+/// protected by a password or only joinable with invites.
+#[debug_handler]
+pub async fn create_room(
+	Extension(room_db): Extension<RoomDb>,
+	jwt_keys: Extension<JwtKeys>,
+	claims: Authentication,
+	Json(create_room): Json<CreateRoom>,
+) -> Result<Json<CreateRoomResponse>, ServerError> {
+	if room_db.is_shutting_down() {
+		return Err(ServerError::ShuttingDown);
+	}
+	// Hashing is slow on purpose, so it runs on the blocking thread pool.
+	let password_hash = match create_room.password.filter(|password| !password.is_empty()) {
+		Some(password) => Some(task::spawn_blocking(move || PasswordHash::new(&password)).await?),
+		None => None,
+	};
+	let access = RoomAccess {
+		password_hash,
+		invite_only: create_room.invite_only,
+		public: create_room.public,
+		creator: Some(claims.user_data.id),
+	};
+	let (id, _room) = room_db.create(create_room.settings, access).await;
+
+	let invite = jwt_keys.encode_invite(&InviteClaims::new(id))?;
+	Ok(Json(CreateRoomResponse { id, invite }))
+}
+
+/// Handler for retrieving the current state of a room. Requires the password
+/// or an invite if the room is protected.
+#[debug_handler]
+pub async fn room_state(
+	Extension(room_db): Extension<RoomDb>,
+	jwt_keys: Extension<JwtKeys>,
+	_claims: Authentication,
+	Path(room_id): Path<Uuid>,
+	Query(join): Query<JoinQuery>,
+) -> Result<Json<websocket::RoomState>, ServerError> {
+	let room = room_db.get(room_id).await.ok_or(ServerError::RoomNotFound)?;
+	check_access(&room, &jwt_keys, room_id, &join).await?;
+	Ok(Json(room.state().await))
+}
+
+/// Handler for closing a room, disconnecting all members. Only the host and the
+/// creator of the room can close it.
+#[debug_handler]
+pub async fn close_room(
+	Extension(room_db): Extension<RoomDb>,
+	claims: Authentication,
+	Path(room_id): Path<Uuid>,
+) -> Result<StatusCode, ServerError> {
+	let room = room_db.get(room_id).await.ok_or(ServerError::RoomNotFound)?;
+	let id = claims.user_data.id;
+	if room.host().await != Some(id) && room.access().creator != Some(id) {
+		return Err(ServerError::Forbidden(
+			"Only the host or creator can close the room".to_owned(),
+		));
+	}
+
+	room_db.close(room_id).await;
+	Ok(StatusCode::NO_CONTENT)
 }
 
-/// Handler for upgrading to web-sockets
+/// Handler for retrieving the access requirements of a room and checking
+/// whether a password or invite grants access.
+#[debug_handler]
+pub async fn room_access(
+	Extension(room_db): Extension<RoomDb>,
+	jwt_keys: Extension<JwtKeys>,
+	_claims: Authentication,
+	Path(room_id): Path<Uuid>,
+	Query(join): Query<JoinQuery>,
+) -> Result<Json<RoomAccessInfo>, ServerError> {
+	let room = room_db.get(room_id).await.ok_or(ServerError::RoomNotFound)?;
+	let invited = is_invited(&jwt_keys, room_id, join.invite.as_deref());
+	Ok(Json(room.access().info(join.password, invited).await?))
+}
+
+/// Handler for the host to retrieve a new invite token for the room.
+#[debug_handler]
+pub async fn room_invite(
+	Extension(room_db): Extension<RoomDb>,
+	jwt_keys: Extension<JwtKeys>,
+	claims: Authentication,
+	Path(room_id): Path<Uuid>,
+) -> Result<Json<CreateRoomResponse>, ServerError> {
+	let room = room_db.get(room_id).await.ok_or(ServerError::RoomNotFound)?;
+	if room.host().await != Some(claims.user_data.id) {
+		return Err(ServerError::Forbidden("Only the host can invite".to_owned()));
+	}
+
+	let invite = jwt_keys.encode_invite(&InviteClaims::new(room_id))?;
+	Ok(Json(CreateRoomResponse { id: room_id, invite }))
+}
+
+/// Handler for upgrading to web-sockets. Checks the room's password or invite
+/// before upgrading.
 #[debug_handler]
 pub async fn websocket_upgrade(
-	room_db: Extension<RoomDb>,
+	Extension(room_db): Extension<RoomDb>,
+	jwt_keys: Extension<JwtKeys>,
 	claims: Authentication,
 	ws: WebSocketUpgrade,
+	headers: HeaderMap,
+	Path(room_id): Path<Uuid>,
+	Query(join): Query<JoinQuery>,
+) -> Result<impl IntoResponse, ServerError> {
+	if room_db.is_shutting_down() {
+		return Err(ServerError::ShuttingDown);
+	}
+	let room = room_db.get(room_id).await.ok_or(ServerError::RoomNotFound)?;
+	check_access(&room, &jwt_keys, room_id, &join).await?;
+
+	let codec = headers
+		.get(header::SEC_WEBSOCKET_PROTOCOL)
+		.and_then(|protocols| protocols.to_str().ok())
+		.and_then(Codec::negotiate);
+	Ok(ws.protocols(codec.map(Codec::subprotocol)).on_upgrade(move |ws| {
+		let ws = Socket::new(ws, codec.unwrap_or_default());
+		connection(ws, room_db, room, claims.0.user_data, join)
+	}))
+}
+
+/// Handler for the server-sent events fallback of the websocket, streaming the
+/// room's events. The client posts its messages to [`post_message`] with the
+/// same stream ID.
+#[debug_handler]
+pub async fn event_stream(
+	Extension(room_db): Extension<RoomDb>,
+	Extension(streams): Extension<EventStreams>,
+	jwt_keys: Extension<JwtKeys>,
+	claims: Authentication,
+	Path(room_id): Path<Uuid>,
+	Query(join): Query<JoinQuery>,
+	Query(stream): Query<StreamQuery>,
+) -> Result<impl IntoResponse, ServerError> {
+	if room_db.is_shutting_down() {
+		return Err(ServerError::ShuttingDown);
+	}
+	let room = room_db.get(room_id).await.ok_or(ServerError::RoomNotFound)?;
+	check_access(&room, &jwt_keys, room_id, &join).await?;
+
+	let user_data = claims.0.user_data;
+	let (socket, events) = streams
+		.open(stream.stream, room_id, user_data.id)
+		.ok_or_else(|| ServerError::InvalidInput("Stream ID is taken".to_owned()))?;
+	tokio::spawn(async move {
+		connection(socket, room_db, room, user_data, join).await;
+		streams.close(stream.stream);
+	});
+
+	let events = ReceiverStream::new(events).map(Ok::<_, Infallible>);
+	Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Handler for posting a message to the user's event stream.
+#[debug_handler]
+pub async fn post_message(
+	Extension(streams): Extension<EventStreams>,
+	claims: Authentication,
 	Path(room_id): Path<Uuid>,
-) -> impl IntoResponse {
-	ws.on_upgrade(move |ws| async move {
-		let room = room_db.lock().await.entry(room_id).or_default().clone();
-		let user_data = claims.0.user_data;
+	Query(stream): Query<StreamQuery>,
+	Json(msg): Json<ClientMessage>,
+) -> Result<StatusCode, ServerError> {
+	streams
+		.post(stream.stream, room_id, claims.0.user_data.id, msg)
+		.await
+		.ok_or(ServerError::StreamNotFound)?;
+	Ok(StatusCode::ACCEPTED)
+}
 
-		tracing::debug!("Connecting websocket user.");
-		websocket_handler(ws, &room, &user_data).await;
-		tracing::debug!("Disconnecting websocket user.");
+/// Run the connection of the user to the room and leave the room after it
+/// ended. Shutting down waits for the connection, but not for the reconnection
+/// grace period.
+async fn connection(
+	socket: Socket,
+	room_db: RoomDb,
+	room: Arc<RoomState>,
+	user_data: UserData,
+	join: JoinQuery,
+) {
+	tracing::debug!("Connecting user as {:?}.", join.role);
+	let tracked = room_db.track_connection();
+	let connections = METRICS.connections.with_label_values(&[socket.transport()]);
+	connections.inc();
+	let limiter = MessageLimiter::new(room_db.message_limit());
+	let joined = websocket_handler(socket, &room, &user_data, &join, limiter).await;
+	connections.dec();
+	drop(tracked);
+	tracing::debug!("Disconnecting user.");
 
-		room.leave_member(&user_data.name).await;
-		if room.is_empty().await {
-			room_db.lock().await.remove(&room_id);
+	match joined {
+		Some((Role::Player, left)) => {
+			room.leave_member(user_data.id).await;
+			// Keep the seat for reconnecting, unless the member left on purpose.
+			if let Some(disconnected_at) = room.disconnected_since(user_data.id).await {
+				if !left {
+					tokio::time::sleep(room_db.reconnect_grace()).await;
+				}
+				room.expire_member(user_data.id, disconnected_at).await;
+			}
+		}
+		Some((Role::Spectator, _)) => {
+			room.leave_spectator().await;
 		}
-	})
+		None => {}
+	}
 }
 
-/// Websocket handlers
+/// Check whether the invite token is a valid invite for the room.
+fn is_invited(jwt_keys: &JwtKeys, room_id: Uuid, invite: Option<&str>) -> bool {
+	invite
+		.and_then(|invite| jwt_keys.decode_invite(invite).ok())
+		.is_some_and(|invite| invite.room_id == room_id)
+}
+
+/// Check whether the password or invite grants access to the room.
+async fn check_access(
+	room: &RoomState,
+	jwt_keys: &JwtKeys,
+	room_id: Uuid,
+	join: &JoinQuery,
+) -> Result<(), ServerError> {
+	let invited = is_invited(jwt_keys, room_id, join.invite.as_deref());
+	if room.access().allows_blocking(join.password.clone(), invited).await? {
+		Ok(())
+	} else {
+		Err(ServerError::Forbidden("Wrong password or missing invite".to_owned()))
+	}
+}
+
+/// Websocket handler. Returns the role the user joined the room with and
+/// whether they left on purpose, None means the user could not join.
 async fn websocket_handler(
-	mut ws: WebSocket,
+	mut ws: Socket,
 	room: &RoomState,
 	user_data: &UserData,
-) -> Option<()> {
-	room.join_member(user_data.clone()).await?;
-	let state = ServerMessage::State(room.state().await);
-	ws.send(Message::Text(serde_json::to_string(&state).ok()?)).await.ok()?;
+	join: &JoinQuery,
+	mut limiter: MessageLimiter,
+) -> Option<(Role, bool)> {
+	let welcome = handshake(&mut ws, room).await?;
+	if room.is_banned(user_data).await {
+		let reason = Some("You are banned from this room.".to_owned());
+		let kicked = ServerMessage::Kicked { id: user_data.id, reason };
+		close_kicked(&mut ws, &ServerEvent { seq: room.seq(), message: kicked }).await;
+		return None;
+	}
+
+	let session = match join.role {
+		Role::Player => {
+			room.join_member(user_data.clone()).await?;
+			if welcome.capabilities.contains(&Capability::Resume) {
+				room.resume_session(user_data.id, join.resume).await
+			} else {
+				None
+			}
+		}
+		Role::Spectator => {
+			room.join_spectator().await?;
+			None
+		}
+	};
+
+	let mut latency = session.and_then(|(_token, latency)| latency);
+	let resume = session.map(|(token, _latency)| token);
+	let capabilities = &welcome.capabilities;
+	let left = websocket_loop(
+		&mut ws,
+		room,
+		user_data,
+		join.role,
+		capabilities,
+		resume,
+		&mut latency,
+		&mut limiter,
+	)
+	.await;
+	if let Some(token) = resume {
+		room.save_session(user_data.id, token, latency).await;
+	}
+	Some((join.role, left.is_some()))
+}
+
+/// Wait for the client's hello and answer with the negotiated protocol version
+/// and capabilities. Closes the websocket and returns None if the client does
+/// not send a hello in time or its protocol version is not supported anymore.
+async fn handshake(ws: &mut Socket, room: &RoomState) -> Option<Hello> {
+	let first_message = tokio::time::timeout(HELLO_TIMEOUT, ws.recv()).await;
+
+	let hello = match first_message {
+		Ok(None) => return None,
+		Ok(Some(Ok(ClientMessage::Hello(hello))))
+			if hello.version < websocket::MIN_PROTOCOL_VERSION =>
+		{
+			Err(ClientError::IncompatibleProtocol(format!(
+				"Protocol version {} is not supported anymore, please update the client",
+				hello.version
+			)))
+		}
+		Ok(Some(Ok(ClientMessage::Hello(hello)))) => Ok(hello),
+		Ok(Some(Err(err))) => Err(err.into()),
+		Ok(Some(Ok(_))) | Err(_) => Err(ClientError::IncompatibleProtocol(
+			"Expected a hello with the protocol version, please update the client".to_owned(),
+		)),
+	};
+	let hello = match hello {
+		Ok(hello) => hello,
+		Err(err) => {
+			send_error(ws, room.seq(), &err).await;
+			return None;
+		}
+	};
 
+	// Newer clients are downgraded to the server's version.
+	let welcome = Hello {
+		version: hello.version.min(websocket::PROTOCOL_VERSION),
+		capabilities: Capability::ALL
+			.into_iter()
+			.filter(|capability| hello.capabilities.contains(capability))
+			.collect(),
+	};
+	tracing::debug!("Websocket client speaks {welcome:?}.");
+	let message = ServerMessage::Welcome(welcome.clone());
+	ws.send(&ServerEvent { seq: room.seq(), message }).await?;
+	Some(welcome)
+}
+
+/// Send the room's events to the websocket and handle the client's messages
+/// until the connection closes. Messages of capabilities the client does not
+/// support are left out. Messages over the rate limit are dropped. Returns Some
+/// if the user left on purpose.
+#[allow(clippy::too_many_arguments)] // state of the connection
+async fn websocket_loop(
+	ws: &mut Socket,
+	room: &RoomState,
+	user_data: &UserData,
+	role: Role,
+	capabilities: &[Capability],
+	resume: Option<Uuid>,
+	latency: &mut Option<Latency>,
+	limiter: &mut MessageLimiter,
+) -> Option<()> {
 	let mut events = room.subscribe();
+	let mut seq = send_state(ws, room).await?;
+	if let Some(token) = resume {
+		ws.send(&ServerEvent { seq, message: ServerMessage::Resume(token) }).await?;
+	}
+
+	let pings = capabilities.contains(&Capability::Latency);
+	let ticks = capabilities.contains(&Capability::Ticks);
+	let mut ping_interval = tokio::time::interval(PING_INTERVAL);
 	loop {
 		tokio::select! {
 			event = events.recv() => {
+				let event = match event {
+					Ok(event) => event,
+					Err(RecvError::Lagged(skipped)) => {
+						METRICS.broadcast_lags.inc();
+						tracing::debug!("Websocket lagged behind by {skipped} events, resyncing..");
+						seq = send_state(ws, room).await?;
+						continue;
+					}
+					Err(RecvError::Closed) => break,
+				};
+				if !ticks && matches!(event.message, ServerMessage::Tick(_)) {
+					continue;
+				}
+				seq = event.seq;
+				let kicked = matches!(&event.message, ServerMessage::Kicked { id, .. } if *id == user_data.id);
+				if role == Role::Player && kicked {
+					close_kicked(ws, &event).await;
+					break;
+				}
+				if matches!(event.message, ServerMessage::Closed) {
+					close_with(ws, &event, websocket::CLOSE_CODE_CLOSED, "Room closed".to_owned()).await;
+					break;
+				}
+				if matches!(event.message, ServerMessage::Restarting) {
+					close_with(ws, &event, websocket::CLOSE_CODE_RESTARTING, "Server restarting".to_owned()).await;
+					break;
+				}
+
 				tracing::trace!("Sending room event via websocket..");
-				ws.send(Message::Text(serde_json::to_string(&event.ok()?).ok()?)).await.ok()?;
+				ws.send(&event).await?;
 			}
 
-			msg = ws.recv() => match msg? {
-				Ok(Message::Text(msg)) => {
+			_ = ping_interval.tick(), if pings => {
+				ws.send(&ServerEvent { seq, message: ServerMessage::Ping(now_millis()) }).await?;
+			}
+
+			msg = ws.recv() => match msg {
+				Some(Ok(client_msg)) => {
 					tracing::trace!("Received message via websocket..");
-					let client_msg: ClientMessage = serde_json::from_str(&msg).ok()?;
-					handle_client_message(room, user_data, client_msg).await?;
+					if let Err(err) = limiter.check() {
+						send_error(ws, seq, &err).await?;
+						continue;
+					}
+					match client_msg {
+						ClientMessage::Resync => seq = send_state(ws, room).await?,
+						ClientMessage::Leave => return Some(()),
+						client_msg => {
+							let timer = METRICS
+								.message_duration
+								.with_label_values(&[metrics::message_type(&client_msg)])
+								.start_timer();
+							let handled = handle_client_message(room, user_data, role, latency, client_msg).await;
+							timer.observe_duration();
+							if let Err(err) = handled {
+								send_error(ws, seq, &err).await?;
+							}
+						}
+					}
 				}
-
-				Ok(Message::Close(_)) | Err(_) => break,
-				_ => {}
+				Some(Err(err)) => return send_error(ws, seq, &err.into()).await.and(None),
+				None => break,
 			}
 		}
 	}
@@ -97,27 +525,162 @@ async fn websocket_handler(
 	None
 }
 
-/// Handle a message from a client
+/// Send the full room state to the websocket and return its sequence number.
+async fn send_state(ws: &mut Socket, room: &RoomState) -> Option<u64> {
+	let state = room.state_event().await;
+	ws.send(&state).await?;
+	Some(state.seq)
+}
+
+/// Report the error to the websocket client. Fatal errors close the websocket
+/// and return None.
+async fn send_error(ws: &mut Socket, seq: u64, error: &ClientError) -> Option<()> {
+	tracing::debug!("Websocket client error: {error}");
+	let event = ServerEvent { seq, message: error.to_message() };
+	if error.is_fatal() {
+		close_with(ws, &event, error.code().close_code(), error.to_string()).await;
+		None
+	} else {
+		ws.send(&event).await
+	}
+}
+
+/// Send the kick message to the websocket and close it with the kick reason.
+async fn close_kicked(ws: &mut Socket, kicked: &ServerEvent) -> Option<()> {
+	let reason = match &kicked.message {
+		ServerMessage::Kicked { reason: Some(reason), .. } => reason.clone(),
+		_ => "Kicked by the host".to_owned(),
+	};
+	close_with(ws, kicked, websocket::CLOSE_CODE_KICKED, reason).await
+}
+
+/// Send the final event to the websocket and close it with the code and
+/// reason.
+async fn close_with(ws: &mut Socket, event: &ServerEvent, code: u16, reason: String) -> Option<()> {
+	ws.send(event).await?;
+	ws.close(code, reason).await
+}
+
+/// Handle a message from a client. Messages that are not allowed or do not fit
+/// the room are rejected with an error for the client.
+#[allow(clippy::too_many_lines)] // one short arm per message
 async fn handle_client_message(
 	room: &RoomState,
 	user_data: &UserData,
+	role: Role,
+	latency: &mut Option<Latency>,
 	client_msg: ClientMessage,
-) -> Option<()> {
-	let is_host = room.host().await == user_data.name;
+) -> Result<(), ClientError> {
+	if role == Role::Spectator && !matches!(client_msg, ClientMessage::Pong { .. }) {
+		return Err(ClientError::Spectator);
+	}
+
+	let is_host = room.host().await == Some(user_data.id);
+	let require_host = |action| if is_host { Ok(()) } else { Err(ClientError::HostOnly(action)) };
+	let unknown_member = || ClientError::InvalidAction("Unknown member");
 	match client_msg {
-		ClientMessage::Buzz => {
-			if room.buzzed().await.is_none() {
-				room.set_buzzed(Some(user_data.name.clone())).await?;
+		ClientMessage::Buzz { pressed_at } => {
+			if room.is_locked_out(user_data.id).await {
+				return Err(ClientError::InvalidAction("You are locked out from buzzing"));
+			}
+			let latency = *latency;
+			let pressed_at =
+				latency.zip(pressed_at).map(|(latency, time)| latency.to_server_time(time));
+			if room.buzz(user_data, pressed_at, latency.map(|latency| latency.rtt)).await.is_some()
+			{
+				METRICS.buzzes.inc();
 			}
 		}
 		ClientMessage::FreeBuzzer => {
-			if is_host {
-				room.set_buzzed(None).await?;
+			require_host("free the buzzer")?;
+			room.free_buzzer().await;
+		}
+		ClientMessage::OpenRound { countdown_secs } => {
+			require_host("open a round")?;
+			let countdown = countdown_secs.map(|secs| Duration::from_secs(secs.into()));
+			room.open_round(countdown).await;
+		}
+		ClientMessage::LockRound => {
+			require_host("lock the buzzer")?;
+			room.lock_round().await.ok_or(ClientError::InvalidAction("The buzzer is not open"))?;
+		}
+		ClientMessage::RevealRound => {
+			require_host("reveal the answer")?;
+			room.reveal_round().await;
+		}
+		ClientMessage::ResetRound => {
+			require_host("reset the round")?;
+			room.reset_round().await;
+		}
+		ClientMessage::NextBuzzer => {
+			require_host("pass on to the next buzzer")?;
+			room.next_buzzer().await.ok_or(ClientError::InvalidAction("Nobody buzzed"))?;
+		}
+		ClientMessage::AwardPoints { id, points } => {
+			require_host("award points")?;
+			room.award_points(id, points).await.ok_or_else(unknown_member)?;
+		}
+		ClientMessage::CreateTeam(team) => {
+			require_host("create teams")?;
+			room.create_team(&team)
+				.await
+				.ok_or(ClientError::InvalidName("Team names must be unique and not empty"))?;
+		}
+		ClientMessage::RemoveTeam(team) => {
+			require_host("remove teams")?;
+			room.remove_team(&team).await.ok_or(ClientError::InvalidAction("Unknown team"))?;
+		}
+		ClientMessage::JoinTeam(team) => {
+			if room.teams_fixed().await {
+				return Err(ClientError::InvalidAction("Teams cannot change during a question"));
 			}
+			room.set_team(user_data.id, team)
+				.await
+				.ok_or(ClientError::InvalidAction("Unknown team"))?;
 		}
-		ClientMessage::Leave => {
-			return None;
+		ClientMessage::AssignTeam { id, team } => {
+			require_host("assign teams")?;
+			if room.teams_fixed().await {
+				return Err(ClientError::InvalidAction("Teams cannot change during a question"));
+			}
+			room.set_team(id, team)
+				.await
+				.ok_or(ClientError::InvalidAction("Unknown member or team"))?;
+		}
+		ClientMessage::TransferHost(id) => {
+			require_host("hand over the host role")?;
+			room.transfer_host(id).await.ok_or_else(unknown_member)?;
+		}
+		ClientMessage::Kick { id, reason } => {
+			require_host("kick members")?;
+			if id == user_data.id {
+				return Err(ClientError::InvalidAction("You cannot kick yourself"));
+			}
+			room.kick(id, reason).await.ok_or_else(unknown_member)?;
+		}
+		ClientMessage::Ban { id, reason } => {
+			require_host("ban members")?;
+			if id == user_data.id {
+				return Err(ClientError::InvalidAction("You cannot ban yourself"));
+			}
+			room.ban(id, reason).await;
+		}
+		ClientMessage::JudgeAnswer { correct } => {
+			require_host("judge answers")?;
+			room.judge_answer(correct).await.ok_or(ClientError::InvalidAction("Nobody buzzed"))?;
+		}
+		ClientMessage::ChangeName(name) => {
+			room.rename_member(user_data.id, &name)
+				.await
+				.ok_or(ClientError::InvalidName("Names must have 1 to 32 characters"))?;
+		}
+		// Handled by the websocket loop, which owns the connection.
+		ClientMessage::Resync | ClientMessage::Leave => {}
+		// The protocol was negotiated already.
+		ClientMessage::Hello(_) => {}
+		ClientMessage::Pong { server_time, client_time } => {
+			*latency = Some(Latency::measure(server_time, client_time));
 		}
 	};
-	Some(())
+	Ok(())
 }
@@ -0,0 +1,62 @@
+//! Graceful shutdown of the server
+
+use std::time::Duration;
+
+use tokio::signal;
+
+use super::RoomDb;
+
+/// Graceful shutdown of the server on SIGTERM or Ctrl+C
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+	/// Room database to shut down
+	room_db: RoomDb,
+	/// Maximum time to wait for connections to close
+	drain_timeout: Duration,
+}
+
+impl Shutdown {
+	/// Create the graceful shutdown of the rooms.
+	#[must_use]
+	pub const fn new(room_db: RoomDb, drain_timeout: Duration) -> Self {
+		Self { room_db, drain_timeout }
+	}
+
+	/// Wait for SIGTERM or Ctrl+C and shut down the rooms, disconnecting all
+	/// clients. Completes when the server should stop.
+	pub async fn wait(self) {
+		terminate_signal().await;
+		tracing::info!("Shutting down, waiting up to {:?} for connections..", self.drain_timeout);
+		self.room_db.shut_down(self.drain_timeout).await;
+		tracing::info!("Stopping server.");
+	}
+}
+
+/// Wait for SIGTERM or Ctrl+C.
+async fn terminate_signal() {
+	let ctrl_c = async {
+		if let Err(err) = signal::ctrl_c().await {
+			tracing::error!("Could not listen for Ctrl+C: {err}");
+			std::future::pending::<()>().await;
+		}
+	};
+	#[cfg(unix)]
+	let terminate = async {
+		match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+			Ok(mut sigterm) => {
+				sigterm.recv().await;
+			}
+			Err(err) => {
+				tracing::error!("Could not listen for SIGTERM: {err}");
+				std::future::pending::<()>().await;
+			}
+		}
+	};
+	#[cfg(not(unix))]
+	let terminate = std::future::pending::<()>();
+
+	tokio::select! {
+		() = ctrl_c => {}
+		() = terminate => {}
+	}
+}
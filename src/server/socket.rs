@@ -0,0 +1,177 @@
+//! Client connections via websocket or server-sent events
+
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex, PoisonError},
+};
+
+use api_types::{
+	codec::{Codec, CodecError, Frame},
+	websocket::{ClientMessage, ServerEvent},
+};
+use axum::{
+	extract::ws::{CloseFrame, Message, WebSocket},
+	response::sse,
+};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Number of events buffered for slow event stream clients.
+const EVENT_BUFFER: usize = 64;
+/// Number of posted messages buffered for event streams.
+const MESSAGE_BUFFER: usize = 16;
+
+/// Connection of a client
+enum Transport {
+	/// Websocket, with the codec negotiated by subprotocol
+	WebSocket(Box<WebSocket>, Codec),
+	/// Server-sent events in JSON, with the client's messages posted via HTTP
+	EventStream {
+		/// Events for the stream's response
+		events: mpsc::Sender<sse::Event>,
+		/// Messages posted by the client
+		messages: mpsc::Receiver<ClientMessage>,
+	},
+}
+
+/// Connection of a client, encoding and decoding messages for its transport
+pub struct Socket {
+	/// Transport of the connection
+	transport: Transport,
+}
+
+impl Socket {
+	/// Wrap the websocket with the negotiated codec.
+	pub fn new(ws: WebSocket, codec: Codec) -> Self {
+		Self { transport: Transport::WebSocket(Box::new(ws), codec) }
+	}
+
+	/// Name of the connection's transport.
+	pub const fn transport(&self) -> &'static str {
+		match self.transport {
+			Transport::WebSocket(..) => "websocket",
+			Transport::EventStream { .. } => "event_stream",
+		}
+	}
+
+	/// Send an event. None means the connection is gone.
+	pub async fn send(&mut self, event: &ServerEvent) -> Option<()> {
+		match &mut self.transport {
+			Transport::WebSocket(ws, codec) => {
+				let msg = match codec.encode(event) {
+					Ok(Frame::Text(text)) => Message::Text(text),
+					Ok(Frame::Binary(data)) => Message::Binary(data),
+					Err(err) => {
+						tracing::error!("Could not encode websocket message: {err}");
+						return None;
+					}
+				};
+				ws.send(msg).await.ok()
+			}
+			Transport::EventStream { events, .. } => {
+				let event = sse::Event::default().json_data(event).ok()?;
+				events.send(event).await.ok()
+			}
+		}
+	}
+
+	/// Receive the next message of the client, skipping control frames. None
+	/// means the connection was closed.
+	pub async fn recv(&mut self) -> Option<Result<ClientMessage, CodecError>> {
+		match &mut self.transport {
+			Transport::WebSocket(ws, codec) => loop {
+				let frame = match ws.recv().await?.ok()? {
+					Message::Text(text) => Frame::Text(text),
+					Message::Binary(data) => Frame::Binary(data),
+					Message::Ping(_) | Message::Pong(_) => continue,
+					Message::Close(_) => return None,
+				};
+				return Some(codec.decode(&frame));
+			},
+			Transport::EventStream { events, messages } => tokio::select! {
+				msg = messages.recv() => msg.map(Ok),
+				() = events.closed() => None,
+			},
+		}
+	}
+
+	/// Close the connection with the code and reason. Event streams receive a
+	/// `close` event with the code and reason before they end.
+	pub async fn close(&mut self, code: u16, mut reason: String) -> Option<()> {
+		// Close frame reasons are limited to 123 bytes.
+		while reason.len() > 123 {
+			reason.pop();
+		}
+		match &mut self.transport {
+			Transport::WebSocket(ws, _) => {
+				let close = CloseFrame { code, reason: reason.into() };
+				ws.send(Message::Close(Some(close))).await.ok()
+			}
+			Transport::EventStream { events, .. } => {
+				let event = sse::Event::default().event("close").data(format!("{code} {reason}"));
+				events.send(event).await.ok()
+			}
+		}
+	}
+}
+
+/// Event stream the client posts its messages to
+struct EventStream {
+	/// Room of the stream
+	room_id: Uuid,
+	/// User of the stream
+	user_id: Uuid,
+	/// Messages to the stream's connection
+	messages: mpsc::Sender<ClientMessage>,
+}
+
+/// Open event streams by stream ID
+#[derive(Clone, Default)]
+pub struct EventStreams(Arc<Mutex<HashMap<Uuid, EventStream>>>);
+
+impl EventStreams {
+	/// Open an event stream of the user in the room. Returns the connection and
+	/// the events for the stream's response, None if the stream ID is taken.
+	pub fn open(
+		&self,
+		id: Uuid,
+		room_id: Uuid,
+		user_id: Uuid,
+	) -> Option<(Socket, mpsc::Receiver<sse::Event>)> {
+		let mut streams = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+		if streams.contains_key(&id) {
+			return None;
+		}
+
+		let (events, events_receiver) = mpsc::channel(EVENT_BUFFER);
+		let (messages_sender, messages) = mpsc::channel(MESSAGE_BUFFER);
+		streams.insert(id, EventStream { room_id, user_id, messages: messages_sender });
+		let socket = Socket { transport: Transport::EventStream { events, messages } };
+		Some((socket, events_receiver))
+	}
+
+	/// Remove the event stream after its connection ended.
+	pub fn close(&self, id: Uuid) {
+		self.0.lock().unwrap_or_else(PoisonError::into_inner).remove(&id);
+	}
+
+	/// Pass on a message posted by the user to their event stream in the room.
+	/// None means there is no such stream.
+	pub async fn post(
+		&self,
+		id: Uuid,
+		room_id: Uuid,
+		user_id: Uuid,
+		msg: ClientMessage,
+	) -> Option<()> {
+		let messages = self
+			.0
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.get(&id)
+			.filter(|stream| stream.room_id == room_id && stream.user_id == user_id)?
+			.messages
+			.clone();
+		messages.send(msg).await.ok()
+	}
+}
@@ -1,7 +1,8 @@
 //! Configuration module
 
-use std::net::SocketAddr;
+use std::{net::SocketAddr, path::PathBuf};
 
+pub use api_types::RoomSettings;
 use config::{ConfigError, Environment, File};
 use serde::{de::Error, Deserialize, Deserializer};
 use tracing::{metadata::ParseLevelError, Level};
@@ -11,11 +12,115 @@ use tracing::{metadata::ParseLevelError, Level};
 pub struct Settings {
 	/// Webserver bind address and port
 	pub bind: SocketAddr,
+	/// Serve HTTPS on the bind address, plain HTTP if not set
+	#[serde(default)]
+	pub tls: Option<TlsSettings>,
 	/// Logging level
 	#[serde(deserialize_with = "deserialize_level")]
 	pub log_level: Level,
 	/// Secret for initializing JWT keys.
 	pub jwt_secret: String,
+	/// Settings for the buzzer rooms
+	#[serde(default)]
+	pub rooms: RoomSettings,
+	/// SQLite database URL to persist rooms in, e.g. `sqlite://buzzer.db`.
+	/// Rooms are only kept in memory if not set.
+	#[serde(default)]
+	pub database_url: Option<String>,
+	/// Seconds after which rooms without members are deleted
+	#[serde(default = "default_room_expiry_secs")]
+	pub room_expiry_secs: u64,
+	/// Seconds that disconnected members keep their seat, host role and team
+	/// to reconnect
+	#[serde(default = "default_reconnect_grace_secs")]
+	pub reconnect_grace_secs: u64,
+	/// Seconds to wait for connections to close when shutting down
+	#[serde(default = "default_shutdown_drain_secs")]
+	pub shutdown_drain_secs: u64,
+	/// Prometheus metrics endpoint, disabled if not set
+	#[serde(default)]
+	pub metrics: Option<MetricsSettings>,
+	/// Rate limits of clients
+	#[serde(default)]
+	pub rate_limits: RateLimits,
+}
+
+/// Rate limits of clients
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct RateLimits {
+	/// Logins and room password checks per IP address
+	pub login: RateLimit,
+	/// Websocket upgrades, event streams and room creations per IP address
+	pub connections: RateLimit,
+	/// Messages per connection
+	pub messages: RateLimit,
+}
+
+impl Default for RateLimits {
+	fn default() -> Self {
+		Self {
+			login: RateLimit { burst: 10, per_second: 0.2 },
+			connections: RateLimit { burst: 20, per_second: 1.0 },
+			messages: RateLimit { burst: 20, per_second: 10.0 },
+		}
+	}
+}
+
+/// Token-bucket rate limit: bursts up to a number of requests are allowed,
+/// afterwards requests are limited to a constant rate
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RateLimit {
+	/// Maximum number of requests in a burst
+	pub burst: u32,
+	/// Sustained number of requests per second
+	pub per_second: f64,
+}
+
+/// Settings of HTTPS
+#[derive(Debug, Clone, Deserialize)]
+pub struct TlsSettings {
+	/// Path of the PEM certificate chain. Reloaded when the file changes.
+	pub cert: PathBuf,
+	/// Path of the PEM private key, PKCS#8 or RSA. Reloaded when the file
+	/// changes.
+	pub key: PathBuf,
+	/// Bind address and port of a plain HTTP listener redirecting to HTTPS,
+	/// e.g. port 80. No redirect if not set.
+	#[serde(default)]
+	pub redirect_bind: Option<SocketAddr>,
+}
+
+/// Settings of the Prometheus metrics endpoint
+#[derive(Debug, Clone, Deserialize)]
+pub struct MetricsSettings {
+	/// Path of the endpoint
+	#[serde(default = "default_metrics_path")]
+	pub path: String,
+	/// Separate bind address and port for the endpoint, e.g. to keep it
+	/// internal. Served with the app if not set.
+	#[serde(default)]
+	pub bind: Option<SocketAddr>,
+}
+
+/// Default value for [`Settings::room_expiry_secs`].
+const fn default_room_expiry_secs() -> u64 {
+	60 * 60 // 1 hour
+}
+
+/// Default value for [`Settings::reconnect_grace_secs`].
+const fn default_reconnect_grace_secs() -> u64 {
+	30
+}
+
+/// Default value for [`Settings::shutdown_drain_secs`].
+const fn default_shutdown_drain_secs() -> u64 {
+	10
+}
+
+/// Default value for [`MetricsSettings::path`].
+fn default_metrics_path() -> String {
+	"/metrics".to_owned()
 }
 
 /// Deserialize a Level
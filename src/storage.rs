@@ -0,0 +1,93 @@
+//! Persistent storage of rooms in an SQLite database
+
+use std::str::FromStr;
+
+use sqlx::{
+	migrate::MigrateError,
+	sqlite::{SqliteConnectOptions, SqlitePool},
+	Row,
+};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::rooms::RoomSnapshot;
+
+/// Storage error type
+#[derive(Debug, Error)]
+pub enum StorageError {
+	/// Database error
+	#[error("Database error: {0}")]
+	Database(#[from] sqlx::Error),
+	/// Database migration error
+	#[error("Database migration error: {0}")]
+	Migration(#[from] MigrateError),
+	/// (De-)Serialization error of stored data
+	#[error("Invalid stored data: {0}")]
+	Serialization(#[from] serde_json::Error),
+	/// Invalid stored room ID
+	#[error("Invalid stored room ID: {0}")]
+	InvalidId(#[from] uuid::Error),
+}
+
+/// SQLite storage backend for rooms.
+#[derive(Debug, Clone)]
+pub struct Storage {
+	/// Database connection pool
+	pool: SqlitePool,
+}
+
+impl Storage {
+	/// Connect to the database at the given URL, creating it if it does not
+	/// exist, and run the migrations.
+	pub async fn connect(url: &str) -> Result<Self, StorageError> {
+		let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+		let pool = SqlitePool::connect_with(options).await?;
+		sqlx::migrate!().run(&pool).await?;
+		Ok(Self { pool })
+	}
+
+	/// Load all stored rooms. Rooms whose data can not be read, e.g. because it
+	/// was stored by an incompatible version, are skipped.
+	pub async fn load_rooms(&self) -> Result<Vec<(Uuid, RoomSnapshot)>, StorageError> {
+		let rows = sqlx::query("SELECT id, data FROM rooms").fetch_all(&self.pool).await?;
+		let mut rooms = Vec::with_capacity(rows.len());
+		for row in rows {
+			let id: String = row.try_get("id")?;
+			let data: String = row.try_get("data")?;
+			match serde_json::from_str(&data) {
+				Ok(snapshot) => rooms.push((id.parse()?, snapshot)),
+				Err(err) => tracing::warn!("Skipping unreadable stored room {id}: {err}"),
+			}
+		}
+		Ok(rooms)
+	}
+
+	/// Save a room, replacing previously stored data of the room.
+	pub async fn save_room(&self, id: Uuid, snapshot: &RoomSnapshot) -> Result<(), StorageError> {
+		let data = serde_json::to_string(snapshot)?;
+		sqlx::query(
+			"INSERT INTO rooms (id, data) VALUES (?, ?) ON CONFLICT (id) DO UPDATE SET data = \
+			 excluded.data",
+		)
+		.bind(id.to_string())
+		.bind(data)
+		.execute(&self.pool)
+		.await?;
+		Ok(())
+	}
+
+	/// Check the connection to the database.
+	pub async fn ping(&self) -> Result<(), StorageError> {
+		sqlx::query("SELECT 1").execute(&self.pool).await?;
+		Ok(())
+	}
+
+	/// Delete a stored room.
+	pub async fn delete_room(&self, id: Uuid) -> Result<(), StorageError> {
+		sqlx::query("DELETE FROM rooms WHERE id = ?")
+			.bind(id.to_string())
+			.execute(&self.pool)
+			.await?;
+		Ok(())
+	}
+}
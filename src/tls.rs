@@ -0,0 +1,269 @@
+//! HTTPS serving with certificate reloading
+
+use std::{
+	convert::Infallible,
+	io::{self, BufReader},
+	net::SocketAddr,
+	path::Path,
+	pin::Pin,
+	sync::{Arc, PoisonError, RwLock, Weak},
+	task::{Context, Poll},
+	time::{Duration, SystemTime},
+};
+
+use axum::{
+	extract::connect_info::Connected,
+	http::{header, uri::PathAndQuery, HeaderMap, StatusCode, Uri},
+	response::Redirect,
+	routing, Router,
+};
+use hyper::server::accept::Accept;
+use rustls::{
+	internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys},
+	NoClientAuth, ServerConfig, TLSError,
+};
+use thiserror::Error;
+use tokio::{
+	io::{AsyncRead, AsyncWrite, ReadBuf},
+	net::{TcpListener, TcpStream},
+	sync::mpsc,
+};
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
+
+use crate::settings::TlsSettings;
+
+/// Interval of checking the certificate and key files for changes.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+/// Time for clients to complete the TLS handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Delay after failing to accept a connection, e.g. because of too many open
+/// files.
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_secs(1);
+/// Number of connections with completed handshakes waiting for the server.
+const ACCEPT_BUFFER: usize = 64;
+
+/// TLS error
+#[derive(Debug, Error)]
+pub enum TlsError {
+	/// IO error
+	#[error("IO error: {0}")]
+	Io(#[from] io::Error),
+	/// Invalid certificate or key
+	#[error("Invalid certificate or key: {0}")]
+	Rustls(#[from] TLSError),
+	/// No PEM certificates in the certificate file
+	#[error("No certificates found in {0}")]
+	NoCertificates(String),
+	/// No PEM private key in the key file
+	#[error("No private key found in {0}")]
+	NoPrivateKey(String),
+}
+
+/// Listener accepting TLS connections for the server
+#[derive(Debug)]
+pub struct TlsListener {
+	/// Connections with completed handshakes
+	connections: mpsc::Receiver<TlsConnection>,
+}
+
+impl TlsListener {
+	/// Load the certificate and bind the listener. Spawns the tasks accepting
+	/// connections and reloading the certificate when its files change.
+	pub async fn bind(addr: SocketAddr, settings: &TlsSettings) -> Result<Self, TlsError> {
+		let modified = modified(settings).await;
+		let config = Arc::new(RwLock::new(load_config(settings).await?));
+		let listener = TcpListener::bind(addr).await?;
+
+		let (sender, connections) = mpsc::channel(ACCEPT_BUFFER);
+		tokio::spawn(reload(settings.clone(), Arc::downgrade(&config), modified));
+		tokio::spawn(accept(listener, config, sender));
+		Ok(Self { connections })
+	}
+}
+
+impl Accept for TlsListener {
+	type Conn = TlsConnection;
+	type Error = Infallible;
+
+	fn poll_accept(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+		self.connections.poll_recv(cx).map(|connection| connection.map(Ok))
+	}
+}
+
+/// TLS connection of a client
+#[derive(Debug)]
+pub struct TlsConnection {
+	/// Encrypted stream
+	stream: TlsStream<TcpStream>,
+	/// Address of the client
+	remote: SocketAddr,
+}
+
+impl Connected<&TlsConnection> for SocketAddr {
+	fn connect_info(target: &TlsConnection) -> Self {
+		target.remote
+	}
+}
+
+impl AsyncRead for TlsConnection {
+	fn poll_read(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		buf: &mut ReadBuf<'_>,
+	) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.stream).poll_read(cx, buf)
+	}
+}
+
+impl AsyncWrite for TlsConnection {
+	fn poll_write(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		buf: &[u8],
+	) -> Poll<io::Result<usize>> {
+		Pin::new(&mut self.stream).poll_write(cx, buf)
+	}
+
+	fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.stream).poll_flush(cx)
+	}
+
+	fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.stream).poll_shutdown(cx)
+	}
+}
+
+/// Accept connections and pass them on after their handshake, until the
+/// server is dropped.
+async fn accept(
+	listener: TcpListener,
+	config: Arc<RwLock<Arc<ServerConfig>>>,
+	sender: mpsc::Sender<TlsConnection>,
+) {
+	loop {
+		let (stream, remote) = tokio::select! {
+			accepted = listener.accept() => match accepted {
+				Ok(accepted) => accepted,
+				Err(err) => {
+					tracing::warn!("Could not accept connection: {err}");
+					tokio::time::sleep(ACCEPT_ERROR_DELAY).await;
+					continue;
+				}
+			},
+			() = sender.closed() => break,
+		};
+
+		// Handshakes run concurrently, so slow clients do not block others.
+		let acceptor =
+			TlsAcceptor::from(config.read().unwrap_or_else(PoisonError::into_inner).clone());
+		let sender = sender.clone();
+		tokio::spawn(async move {
+			match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+				Ok(Ok(stream)) => {
+					sender.send(TlsConnection { stream, remote }).await.ok();
+				}
+				Ok(Err(err)) => tracing::debug!("TLS handshake with {remote} failed: {err}"),
+				Err(_) => tracing::debug!("TLS handshake with {remote} timed out"),
+			}
+		});
+	}
+}
+
+/// Periodically reload the certificate when its files changed, until the
+/// listener is dropped. Keeps the previous certificate if the new one is
+/// invalid.
+async fn reload(
+	settings: TlsSettings,
+	config: Weak<RwLock<Arc<ServerConfig>>>,
+	mut last_modified: (Option<SystemTime>, Option<SystemTime>),
+) {
+	let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+	loop {
+		interval.tick().await;
+		let config = match config.upgrade() {
+			Some(config) => config,
+			None => break,
+		};
+
+		let modified = modified(&settings).await;
+		if modified == last_modified {
+			continue;
+		}
+		match load_config(&settings).await {
+			Ok(new_config) => {
+				*config.write().unwrap_or_else(PoisonError::into_inner) = new_config;
+				last_modified = modified;
+				tracing::info!("Reloaded TLS certificate.");
+			}
+			// The files might be written right now, so retry next time.
+			Err(err) => tracing::error!("Could not reload TLS certificate: {err}"),
+		}
+	}
+}
+
+/// Modification times of the certificate and key files.
+async fn modified(settings: &TlsSettings) -> (Option<SystemTime>, Option<SystemTime>) {
+	/// Modification time of the file.
+	async fn modified(path: &Path) -> Option<SystemTime> {
+		tokio::fs::metadata(path).await.and_then(|metadata| metadata.modified()).ok()
+	}
+	(modified(&settings.cert).await, modified(&settings.key).await)
+}
+
+/// Load the certificate and key into a server configuration.
+async fn load_config(settings: &TlsSettings) -> Result<Arc<ServerConfig>, TlsError> {
+	let cert = tokio::fs::read(&settings.cert).await?;
+	let key = tokio::fs::read(&settings.key).await?;
+
+	let cert_path = settings.cert.display().to_string();
+	let certs = certs(&mut BufReader::new(cert.as_slice()))
+		.ok()
+		.filter(|certs| !certs.is_empty())
+		.ok_or(TlsError::NoCertificates(cert_path))?;
+	let key_path = settings.key.display().to_string();
+	let key = pkcs8_private_keys(&mut BufReader::new(key.as_slice()))
+		.ok()
+		.filter(|keys| !keys.is_empty())
+		.or_else(|| rsa_private_keys(&mut BufReader::new(key.as_slice())).ok())
+		.and_then(|keys| keys.into_iter().next())
+		.ok_or(TlsError::NoPrivateKey(key_path))?;
+
+	let mut config = ServerConfig::new(NoClientAuth::new());
+	config.set_single_cert(certs, key)?;
+	// Websockets need HTTP/1.1.
+	config.set_protocols(&[b"http/1.1".to_vec()]);
+	Ok(Arc::new(config))
+}
+
+/// Routes redirecting all requests to HTTPS on the port.
+pub fn redirect_app(https_port: u16) -> Router {
+	Router::new().fallback(routing::any(move |headers: HeaderMap, uri: Uri| async move {
+		redirect_to_https(https_port, &headers, &uri)
+	}))
+}
+
+/// Redirect the request to the same host and path via HTTPS on the port.
+fn redirect_to_https(
+	https_port: u16,
+	headers: &HeaderMap,
+	uri: &Uri,
+) -> Result<Redirect, StatusCode> {
+	let host = headers
+		.get(header::HOST)
+		.and_then(|host| host.to_str().ok())
+		.ok_or(StatusCode::BAD_REQUEST)?;
+	// Strip the port of the HTTP listener, IPv6 addresses are in brackets.
+	let host = host
+		.rsplit_once(':')
+		.filter(|(_host, port)| port.parse::<u16>().is_ok())
+		.map_or(host, |(host, _port)| host);
+	let authority = match https_port {
+		443 => host.to_owned(),
+		port => format!("{host}:{port}"),
+	};
+	let path = uri.path_and_query().map_or("/", PathAndQuery::as_str);
+	Ok(Redirect::permanent(&format!("https://{authority}{path}")))
+}